@@ -1,11 +1,16 @@
 use super::app_graph;
+use crate::clipboard::Clip;
 use crate::dmx;
+use crate::history::{Command, GraphEdit, History};
+use crate::show::{self, Show};
+use crate::views;
 use epi::App;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 enum ScreenState {
     Fixtures,
+    PixelMap,
     Output,
     Nodetree,
     Sequencer,
@@ -17,6 +22,13 @@ enum ScreenState {
 pub struct GuiApp {
     tron_state: crate::TronomicState,
     screen_state: ScreenState,
+    history: History,
+    fixtures_view: views::fixtures::FixturesView,
+    modifiers_view: views::modifiers::ModifiersView,
+    pixel_map_view: views::pixel_map::PixelMapView,
+    clipboard: String,
+    show_path: String,
+    show_sources: bool,
     counter: i32,
     //graph_ctx: egui_node_graph::Context,
 }
@@ -49,6 +61,7 @@ impl App for GuiApp {
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &epi::Frame) {
+        self.handle_shortcuts(ctx);
 
         egui::TopBottomPanel::top("main_menu_bar").show(ctx, |ui| {
             egui::trace!(ui);
@@ -58,15 +71,36 @@ impl App for GuiApp {
             egui::trace!(ui);
             self.bottom_bar_content(ui);
         });
+        egui::SidePanel::right("masters_panel").show(ctx, |ui| {
+            views::masters::masters_ui(ui, &mut self.tron_state.dmx_state.write());
+        });
         egui::CentralPanel::default().show(ctx, |ui| match self.screen_state {
             ScreenState::Fixtures => {
-                ui.label("Fixtures");
+                self.fixtures_view.ui(
+                    ui,
+                    &mut self.tron_state.dmx_state.write(),
+                    &mut self.history,
+                );
+            }
+            ScreenState::PixelMap => {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    self.pixel_map_view.ui(
+                        ui,
+                        &mut self.tron_state.dmx_state.write(),
+                        &self.fixtures_view.selection,
+                    )
+                });
             }
             ScreenState::Nodetree => {
-                app_graph::node_graph(&mut self.tron_state.graph.write(), ui);
+                let mut graph = self.tron_state.graph.write();
+                let edit = app_graph::node_graph(&mut graph, ui);
+                self.history.track_graph(&graph, edit);
             }
             ScreenState::Output => {
-                ui.label("Output configuration");
+                let mut dmx_state = self.tron_state.dmx_state.write();
+                ui.collapsing("Limits, inversion and parking", |ui| self.modifiers_view.ui(ui, &mut dmx_state));
+                ui.collapsing("Safety limiter", |ui| views::output::safety_ui(ui, &mut dmx_state.safety));
+                views::output::output_ui(ui, &mut dmx_state, &mut self.history);
             }
             ScreenState::Sequencer => {
                 ui.label("Sequencer");
@@ -75,7 +109,12 @@ impl App for GuiApp {
                 ui.label("Live environment");
             }
             ScreenState::Plain => {
-                Self::sliders_ui(ui, &mut self.tron_state.dmx_state.write());
+                Self::sliders_ui(
+                    ui,
+                    &mut self.tron_state.dmx_state.write(),
+                    &mut self.history,
+                    &mut self.show_sources,
+                );
             }
         });
 
@@ -92,28 +131,160 @@ impl GuiApp {
     ) -> Self {
         Self {
             tron_state,
+            history: History::default(),
+            fixtures_view: Default::default(),
+            modifiers_view: Default::default(),
+            pixel_map_view: Default::default(),
+            clipboard: String::new(),
+            show_path: show::DEFAULT_SHOW_PATH.to_string(),
+            show_sources: false,
             counter: 0,
             screen_state: ScreenState::Fixtures,
         }
     }
 
-    fn menu_bar_content(&self, ui: &mut egui::Ui) {
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if !ctx.input().pointer.any_down() {
+            self.history.end_merge();
+        }
+
+        let redo_mods = egui::Modifiers {
+            shift: true,
+            ..egui::Modifiers::COMMAND
+        };
+        if ctx.input_mut().consume_key(redo_mods, egui::Key::Z) {
+            self.history.redo(&self.tron_state);
+        } else if ctx.input_mut().consume_key(egui::Modifiers::COMMAND, egui::Key::Z) {
+            self.history.undo(&self.tron_state);
+        }
+
+        if !ctx.wants_keyboard_input() {
+            let events = ctx.input().events.clone();
+            for evt in events {
+                match evt {
+                    egui::Event::Copy => self.copy(ctx),
+                    egui::Event::Paste(text) => self.paste(&text),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn copy(&mut self, ctx: &egui::Context) {
+        let clip = match self.screen_state {
+            ScreenState::Nodetree => {
+                let graph = self.tron_state.graph.read();
+                Clip::Nodes(app_graph::copy_nodes(&graph, &graph.user_state.selection))
+            }
+            ScreenState::Fixtures => {
+                let dmx_state = self.tron_state.dmx_state.read();
+                let fixts: Vec<dmx::Fixture> = self
+                    .fixtures_view
+                    .selection
+                    .iter()
+                    .filter_map(|id| dmx_state.fixts.get(id).cloned())
+                    // Cells come with their fixture
+                    .filter(|f| f.cell_of.is_none())
+                    .map(|f| dmx::Fixture { cells: Vec::new(), ..f })
+                    .collect();
+                let types = fixts
+                    .iter()
+                    .filter_map(|f| Some((f.type_id, dmx_state.library.types.get(&f.type_id)?.clone())))
+                    .collect();
+                Clip::Fixtures { types, fixts }
+            }
+            _ => return,
+        };
+
+        match clip.to_json() {
+            Ok(json) => {
+                ctx.output().copied_text = json.clone();
+                self.clipboard = json;
+            }
+            Err(e) => log::error!("Could not copy selection: {e}"),
+        }
+    }
+
+    fn paste(&mut self, text: &str) {
+        match Clip::from_json(text) {
+            Ok(Clip::Nodes(clip)) => {
+                let mut graph = self.tron_state.graph.write();
+                let before = Box::new((*graph).clone());
+                let new_ids = app_graph::paste_nodes(&mut graph, &clip, app_graph::PASTE_OFFSET);
+                graph.user_state.selection = new_ids;
+                self.history.record_graph(GraphEdit::CreateNode, before, &graph);
+            }
+            Ok(Clip::Fixtures { types, fixts }) => {
+                let mut dmx_state = self.tron_state.dmx_state.write();
+                let mut cmds = Vec::with_capacity(fixts.len());
+                self.fixtures_view.selection.clear();
+                for mut f in fixts {
+                    if let Some(typ) = types.get(&f.type_id) {
+                        f.type_id = dmx_state.library.find_or_add(typ);
+                    }
+                    f.number = dmx_state.next_fixture_number();
+                    match dmx_state.next_free_address(f.universe_id, dmx_state.footprint(&f)) {
+                        Some(start) => f.start = start,
+                        None => {
+                            log::warn!("No free address left in universe {} for pasted fixture", f.universe_id);
+                            continue;
+                        }
+                    }
+                    let fixt_id = dmx_state.add_fixture(f);
+                    self.fixtures_view.selection.push(fixt_id);
+                    cmds.push(Command::Patch {
+                        fixt_id,
+                        before: None,
+                        after: Some(Box::new(dmx_state.fixts[&fixt_id].clone())),
+                    });
+                }
+                if !cmds.is_empty() {
+                    self.history.push(Command::Batch(cmds));
+                }
+            }
+            Err(e) => log::warn!("Clipboard does not contain anything to paste: {e}"),
+        }
+    }
+
+    fn menu_bar_content(&mut self, ui: &mut egui::Ui) {
         egui::menu::bar(ui, |ui| {
             ui.menu_button("File", |ui| {
+                ui.text_edit_singleline(&mut self.show_path);
                 if ui.button("Open").clicked() {
-                    println!("File Open");
+                    match Show::load(&self.show_path) {
+                        Ok(show) => {
+                            show.restore(&self.tron_state);
+                            self.history = History::default();
+                            self.fixtures_view.selection.clear();
+                        }
+                        Err(e) => log::error!("{e}"),
+                    }
+                    ui.close_menu();
                 };
                 if ui.button("Save").clicked() {
-                    println!("File Save");
+                    if let Err(e) = Show::capture(&self.tron_state).save(&self.show_path) {
+                        log::error!("{e}");
+                    }
+                    ui.close_menu();
                 }
             });
 
             ui.menu_button("Edit", |ui| {
+                if ui.add_enabled(self.history.can_undo(), egui::Button::new("Undo")).clicked() {
+                    self.history.undo(&self.tron_state);
+                }
+                if ui.add_enabled(self.history.can_redo(), egui::Button::new("Redo")).clicked() {
+                    self.history.redo(&self.tron_state);
+                }
+                ui.separator();
                 if ui.button("Copy").clicked() {
-                    println!("Edit Copy")
+                    self.copy(ui.ctx());
+                    ui.close_menu();
                 }
-                if ui.button("Paste").clicked() {
-                    println!("Edit Paste")
+                if ui.add_enabled(!self.clipboard.is_empty(), egui::Button::new("Paste")).clicked() {
+                    let text = self.clipboard.clone();
+                    self.paste(&text);
+                    ui.close_menu();
                 }
             });
             ui.menu_button("View", |ui| {
@@ -132,6 +303,9 @@ impl GuiApp {
                 if ui.button("Fixtures").clicked() {
                     self.screen_state = ScreenState::Fixtures;
                 }
+                if ui.button("Pixel map").clicked() {
+                    self.screen_state = ScreenState::PixelMap;
+                }
                 if ui.button("Output Config").clicked() {
                     self.screen_state = ScreenState::Output;
                 }
@@ -165,30 +339,93 @@ impl GuiApp {
     fn sliders_ui(
         ui: &mut egui::Ui,
         dmx_state: &mut parking_lot::RwLockWriteGuard<'_, dmx::DmxState>,
+        history: &mut History,
+        show_sources: &mut bool,
     ) {
+        ui.checkbox(show_sources, "Show sources");
         egui::ScrollArea::both()
             .auto_shrink([true; 2])
             .show(ui, |ui| {
                 ui.vertical(|ui| {
-                    for (un_id, un) in dmx_state.universes.iter_mut() {
-                        ui.vertical(|ui| ui.label(format!("{un_id}")));
+                    for un_id in dmx_state.universe_ids() {
+                        // As sent after the safety limiter, before the first output tick as computed
+                        let out = match dmx_state.sent_frames.get(&un_id) {
+                            Some(frame) => frame.clone(),
+                            None => dmx_state.output_frame(un_id).unwrap_or_default(),
+                        };
+                        let parked: Vec<Option<u8>> = (0..512).map(|i| dmx_state.modifiers.parked(un_id, i)).collect();
+                        let mut park = Vec::new();
+                        let un = dmx_state.universes.get_mut(&un_id).unwrap();
+                        let color: egui::Rgba = un.meta.color.into();
+                        ui.vertical(|ui| ui.colored_label(color, format!("{un_id}: {}", un.meta.label)));
                         ui.horizontal(|ui| {
                             for i in 0..512 {
                                 let c_val = un.get(i);
                                 let mut new = c_val;
-                                GuiApp::one_slider_ui(ui, i + 1, &mut new);
+                                let resp = GuiApp::one_slider_ui(
+                                    ui,
+                                    i + 1,
+                                    &mut new,
+                                    out.get(i).copied().unwrap_or_default(),
+                                    parked[i].is_some(),
+                                    un.attribution(i),
+                                    *show_sources,
+                                );
+                                resp.context_menu(|ui| {
+                                    if parked[i].is_some() {
+                                        if ui.button("Unpark").clicked() {
+                                            park.push((i, None));
+                                            ui.close_menu();
+                                        }
+                                    } else if ui.button("Park at current output").clicked() {
+                                        park.push((i, out.get(i).copied()));
+                                        ui.close_menu();
+                                    }
+                                });
                                 if c_val != new {
+                                    let manual = un.layers().layer(dmx::Source::Manual).and_then(|l| l.get(i));
                                     un.set(i, new);
+                                    history.push(Command::Channel {
+                                        universe: un_id,
+                                        channel: i,
+                                        before: manual,
+                                        after: Some(new),
+                                    });
                                 }
                             }
                         });
+                        for (i, v) in park {
+                            match v {
+                                Some(v) => {
+                                    if let Err(e) = dmx_state.park(un_id, i, v) {
+                                        log::debug!("{e}");
+                                    }
+                                }
+                                None => dmx_state.unpark(un_id, i),
+                            }
+                        }
                     }
                 });
             });
     }
 
-    fn one_slider_ui(ui: &mut egui::Ui, i: usize, chan: &mut u8) {
-        ui.group(|ui| {
+    /// Slider of one channel. Below it the value sent out after curves, limits and parking,
+    /// marked when it differs from the merged value or the channel is parked.
+    fn one_slider_ui(
+        ui: &mut egui::Ui,
+        i: usize,
+        chan: &mut u8,
+        out: u8,
+        parked: bool,
+        attr: Option<dmx::Attribution>,
+        show_source: bool,
+    ) -> egui::Response {
+        let mut frame = egui::Frame::group(ui.style());
+        if let (true, Some(attr)) = (show_source, attr) {
+            frame = frame.fill(Self::source_color(attr.source));
+        }
+        let merged = *chan;
+        let resp = frame.show(ui, |ui| {
             ui.with_layout(egui::Layout::top_down(egui::Align::Center).with_cross_justify(false).with_main_justify(false), |ui| {
                 ui.add(egui::Label::new(i.to_string()).wrap(false));
                 ui.add(
@@ -196,9 +433,39 @@ impl GuiApp {
                         .show_value(false)
                         .vertical(),
                 );
-                ui.add(egui::DragValue::new(chan))
+                ui.add(egui::DragValue::new(chan));
+                if parked {
+                    ui.colored_label(Self::source_color(dmx::Source::Park), format!("P {out}"));
+                } else if out != merged {
+                    ui.colored_label(egui::Color32::from_rgb(230, 150, 40), format!("> {out}"));
+                } else {
+                    ui.weak(out.to_string());
+                }
             });
         });
+        resp.response.on_hover_text(match attr {
+            Some(attr) => {
+                let writer = attr.writer.map(|w| format!(" (writer {w})")).unwrap_or_default();
+                format!(
+                    "{:?}{writer}, changed {:.1} s ago, output {out}",
+                    attr.source,
+                    attr.changed.elapsed().as_secs_f64()
+                )
+            }
+            None => format!("no source, output {out}"),
+        })
+    }
+
+    fn source_color(src: dmx::Source) -> egui::Color32 {
+        match src {
+            dmx::Source::Input => egui::Color32::from_rgb(38, 109, 211),
+            dmx::Source::Graph => egui::Color32::from_rgb(208, 177, 79),
+            dmx::Source::Playback(_) => egui::Color32::from_rgb(150, 90, 200),
+            dmx::Source::Manual => egui::Color32::from_rgb(60, 150, 80),
+            dmx::Source::PixelMap => egui::Color32::from_rgb(60, 190, 190),
+            dmx::Source::Highlight => egui::Color32::from_rgb(220, 220, 220),
+            dmx::Source::Park => egui::Color32::from_rgb(200, 60, 60),
+        }
     }
 
     fn ui_counter(ui: &mut egui::Ui, counter: &mut i32) {
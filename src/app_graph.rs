@@ -1,7 +1,13 @@
 use egui::{self, DragValue};
 use egui_node_graph as eng;
-use egui_node_graph::NodeId;
+use egui_node_graph::{NodeId, NodeTemplateTrait};
+use serde::{Deserialize, Serialize};
 use super::graph::*;
+use crate::dmx::{Attribute, MasterControl, PixelSource, Target};
+use crate::error::DmGuiError;
+use crate::history::GraphEdit;
+use node::Driver;
+use std::collections::HashMap;
 
 pub type NodeGraphType = eng::GraphEditorState<node::Node, port::GType, port::GVal, node::NodeTempl, MyGraphState>;
 
@@ -22,6 +28,7 @@ pub enum GResp {
 pub struct MyGraphState {
     pub time: f64,
     pub frame: f64,
+    pub selection: Vec<NodeId>,
 }
 
 // =========== Then, you need to implement some traits ============
@@ -36,6 +43,7 @@ impl eng::DataTypeTrait for port::GType {
             Self::FNum(_, _) => egui::Color32::from_rgb(238, 207, 109),
             Self::FVec(_, _) => egui::Color32::from_rgb(208, 177, 79),
             Self::Color => egui::Color32::from_rgb(179, 255, 199),
+            Self::Text => egui::Color32::from_rgb(200, 200, 200),
         }
     }
 
@@ -46,6 +54,7 @@ impl eng::DataTypeTrait for port::GType {
             Self::IVec(_, _) => "list of integers",
             Self::FVec(_, _) => "list of floats",
             Self::Color => "color",
+            Self::Text => "text",
         }
     }
 }
@@ -64,6 +73,17 @@ impl eng::NodeTemplateIter for AllNodeTempl {
             node::NodeTempl::Time,
             node::NodeTempl::Sine,
             node::NodeTempl::Output,
+            node::NodeTempl::FixtureIntensity,
+            node::NodeTempl::FixtureColor,
+            node::NodeTempl::FixturePosition,
+            node::NodeTempl::FixtureCapability,
+            node::NodeTempl::FixturePoint,
+            node::NodeTempl::FixtureShutter,
+            node::NodeTempl::Master,
+            node::NodeTempl::Blackout,
+            node::NodeTempl::PixelMap,
+            node::NodeTempl::PixelImage,
+            node::NodeTempl::PixelBuffer,
         ]
     }
 }
@@ -125,6 +145,12 @@ impl eng::WidgetValueTrait for port::GVal {
                     *c = rgba.into();
                 });
             }
+            Self::Text(s) => {
+                ui.horizontal(|ui| {
+                    ui.label(param_name);
+                    ui.text_edit_singleline(s);
+                });
+            }
         }
     }
 }
@@ -143,10 +169,10 @@ impl eng::NodeDataTrait for node::Node {
     // node graph library.
     fn bottom_ui(
         &self,
-        _ui: &mut egui::Ui,
-        _node_id: NodeId,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
         _graph: &eng::Graph<node::Node, port::GType, port::GVal>,
-        _user_state: &Self::UserState,
+        user_state: &Self::UserState,
     ) -> Vec<eng::NodeResponse<GResp>>
     where
         GResp: eng::UserResponseTrait,
@@ -158,19 +184,265 @@ impl eng::NodeDataTrait for node::Node {
 
         let responses = vec![];
 
+        if user_state.selection.contains(&node_id) {
+            ui.label("selected");
+        }
+
         responses
     }
 }
 
-pub fn node_graph(state: &mut parking_lot::RwLockWriteGuard<NodeGraphType>, ui: &mut egui::Ui) {
+pub fn node_graph(state: &mut parking_lot::RwLockWriteGuard<NodeGraphType>, ui: &mut egui::Ui) -> Option<GraphEdit> {
     let graph_response = state.draw_graph_editor(ui, AllNodeTempl);
+    let mut edit = None;
     for node_response in graph_response.node_responses {
-        // Here, we ignore all other graph events. But you may find
-        // some use for them. For example, by playing a sound when a new
-        // connection is created
-        if let eng::NodeResponse::User(user_event) = node_response {
-            match user_event {
+        match node_response {
+            eng::NodeResponse::CreatedNode(_) => edit = Some(GraphEdit::CreateNode),
+            eng::NodeResponse::SelectNode(id) => {
+                let selection = &mut state.user_state.selection;
+                if !ui.input().modifiers.shift {
+                    selection.clear();
+                }
+                if !selection.contains(&id) {
+                    selection.push(id);
+                }
+            }
+            eng::NodeResponse::DeleteNode(id) => {
+                state.user_state.selection.retain(|s| *s != id);
+                edit = Some(GraphEdit::DeleteNode);
+            }
+            eng::NodeResponse::ConnectEventEnded(_) => edit = Some(GraphEdit::Connect),
+            eng::NodeResponse::DisconnectEvent(_) => edit = Some(GraphEdit::Disconnect),
+            eng::NodeResponse::User(user_event) => match user_event {},
+            _ => (),
+        }
+    }
+    if state.selected_node.is_none() {
+        state.user_state.selection.clear();
+    }
+    edit
+}
+
+pub const PASTE_OFFSET: egui::Vec2 = egui::vec2(40., 40.);
+
+/// Graph nodes in a form that survives the clipboard. Connections refer to nodes by their index in
+/// `nodes` and to ports by name, so they can be restored in any graph.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphClip {
+    pub nodes: Vec<ClipNode>,
+    pub connections: Vec<ClipConnection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipNode {
+    pub templ: node::NodeTempl,
+    pub label: String,
+    pub pos: [f32; 2],
+    pub inputs: Vec<(String, port::GVal)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipConnection {
+    pub from_node: usize,
+    pub from_port: String,
+    pub to_node: usize,
+    pub to_port: String,
+}
+
+/// Copies `ids` with their constant values and all connections between them.
+pub fn copy_nodes(state: &NodeGraphType, ids: &[NodeId]) -> GraphClip {
+    let graph = &state.graph;
+    let ids: Vec<NodeId> = ids.iter().copied().filter(|id| graph.nodes.contains_key(*id)).collect();
+
+    let nodes = ids
+        .iter()
+        .map(|id| {
+            let n = &graph.nodes[*id];
+            let pos = state.node_positions.get(*id).copied().unwrap_or(egui::Pos2::ZERO);
+            ClipNode {
+                templ: n.user_data.template().clone(),
+                label: n.label.clone(),
+                pos: [pos.x, pos.y],
+                inputs: n
+                    .inputs
+                    .iter()
+                    .map(|(name, inp)| (name.clone(), graph.inputs[*inp].value.clone()))
+                    .collect(),
             }
+        })
+        .collect();
+
+    let mut connections = Vec::new();
+    for (inp, outp) in graph.iter_connections() {
+        let (to_id, from_id) = (graph.inputs[inp].node, graph.outputs[outp].node);
+        let (to_node, from_node) = match (
+            ids.iter().position(|id| *id == to_id),
+            ids.iter().position(|id| *id == from_id),
+        ) {
+            (Some(t), Some(f)) => (t, f),
+            (_, _) => continue,
+        };
+        let from_port = graph.nodes[from_id].outputs.iter().find(|(_, o)| *o == outp);
+        let to_port = graph.nodes[to_id].inputs.iter().find(|(_, i)| *i == inp);
+        if let (Some((from_port, _)), Some((to_port, _))) = (from_port, to_port) {
+            connections.push(ClipConnection {
+                from_node,
+                from_port: from_port.clone(),
+                to_node,
+                to_port: to_port.clone(),
+            });
         }
     }
+
+    GraphClip { nodes, connections }
+}
+
+/// Inserts the clipped nodes at their original position moved by `offset` and returns the new ids.
+pub fn paste_nodes(state: &mut NodeGraphType, clip: &GraphClip, offset: egui::Vec2) -> Vec<NodeId> {
+    let new_ids: Vec<NodeId> = clip
+        .nodes
+        .iter()
+        .map(|cn| {
+            let id = state.graph.add_node(cn.label.clone(), cn.templ.user_data(), |graph, node_id| {
+                cn.templ.build_node(graph, node_id)
+            });
+            for (name, val) in &cn.inputs {
+                let inp = state.graph.nodes[id].inputs.iter().find(|(n, _)| n == name).map(|(_, i)| *i);
+                if let Some(inp) = inp {
+                    state.graph.inputs[inp].value = val.clone();
+                }
+            }
+            state.node_positions.insert(id, egui::pos2(cn.pos[0], cn.pos[1]) + offset);
+            state.node_order.push(id);
+            id
+        })
+        .collect();
+
+    for con in &clip.connections {
+        let (from_id, to_id) = match (new_ids.get(con.from_node), new_ids.get(con.to_node)) {
+            (Some(f), Some(t)) => (*f, *t),
+            (_, _) => continue,
+        };
+        let outp = state.graph.nodes[from_id].outputs.iter().find(|(n, _)| *n == con.from_port).map(|(_, o)| *o);
+        let inp = state.graph.nodes[to_id].inputs.iter().find(|(n, _)| *n == con.to_port).map(|(_, i)| *i);
+        if let (Some(outp), Some(inp)) = (outp, inp) {
+            state.graph.add_connection(outp, inp);
+        }
+    }
+
+    new_ids
+}
+
+/// Properties of the current evaluation the nodes can read, like `"time"` and `"frame"`
+pub type Props = HashMap<&'static str, port::GVal>;
+
+/// What the nodes with an effect outside of the graph produced in one evaluation
+#[derive(Debug, Default)]
+pub struct Evaluation {
+    /// Channel values of the output nodes as `(node, universe id, values from channel 0 on)`
+    pub channels: Vec<(NodeId, usize, Vec<u8>)>,
+    /// Attributes of the fixture nodes as `(node, target, attribute)`
+    pub attrs: Vec<(NodeId, Target, Attribute)>,
+    /// Master operations of the master and blackout nodes
+    pub controls: Vec<(NodeId, Vec<MasterControl>)>,
+    /// Sources of pixel maps as `(node, [(pixel map name, source)])`
+    pub pixels: Vec<(NodeId, Vec<(String, PixelSource)>)>,
+    /// Nodes that could not be evaluated and why
+    pub errors: Vec<(NodeId, String)>,
+}
+
+/// Writer id of the values a node produces, see [`crate::dmx::Attribution`]
+pub fn node_writer(id: NodeId) -> u64 {
+    slotmap::Key::data(&id).as_ffi()
+}
+
+/// Evaluates every node with an effect outside of the graph. Connected inputs take the value of
+/// the output they are connected to, the others their constant value, and every node producing
+/// values is evaluated at most once.
+pub fn evaluate(graph: &eng::Graph<node::Node, port::GType, port::GVal>, props: &Props) -> Evaluation {
+    let mut eval = Evaluation::default();
+    let mut outputs = HashMap::new();
+    for (id, n) in graph.nodes.iter() {
+        let driver = driver(&n.user_data);
+        if matches!(driver, Driver::Func(_) | Driver::None) {
+            continue;
+        }
+        let res = input_values(graph, id, props, &mut outputs, &mut Vec::new()).and_then(|inp| {
+            match driver {
+                Driver::Ctx(f) => {
+                    for (universe, values) in f(props, inp)? {
+                        let values = values.as_ivec()?.into_iter().map(|v| v.clamp(0, 255) as u8).collect();
+                        eval.channels.push((id, universe, values));
+                    }
+                }
+                Driver::Attr(f) => eval.attrs.extend(f(props, inp)?.into_iter().map(|(t, a)| (id, t, a))),
+                Driver::Control(f) => eval.controls.push((id, f(props, inp)?)),
+                Driver::Pixels(f) => eval.pixels.push((id, f(props, inp)?)),
+                Driver::Func(_) | Driver::None => (),
+            }
+            Ok(())
+        });
+        if let Err(e) = res {
+            eval.errors.push((id, format!("{}: {e}", n.label)));
+        }
+    }
+    eval
+}
+
+/// The driver is not stored with the graph, deserialized nodes get the one of their template
+fn driver(n: &node::Node) -> Driver {
+    match &n.driver {
+        Driver::None => n.template().user_data().driver,
+        d => d.clone(),
+    }
+}
+
+fn input_values(
+    graph: &eng::Graph<node::Node, port::GType, port::GVal>,
+    id: NodeId,
+    props: &Props,
+    outputs: &mut HashMap<NodeId, Vec<port::GVal>>,
+    visiting: &mut Vec<NodeId>,
+) -> Result<Vec<port::GVal>, DmGuiError> {
+    graph.nodes[id]
+        .inputs
+        .iter()
+        .map(|(_, inp)| match graph.connection(*inp) {
+            Some(outp) => {
+                let from = graph.outputs[outp].node;
+                let index = graph.nodes[from].outputs.iter().position(|(_, o)| *o == outp).unwrap_or(0);
+                output_values(graph, from, props, outputs, visiting)?
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| DmGuiError::evaluation(format!("{} has no output {index}", graph.nodes[from].label)))
+            }
+            None => Ok(graph.inputs[*inp].value.clone()),
+        })
+        .collect()
+}
+
+fn output_values(
+    graph: &eng::Graph<node::Node, port::GType, port::GVal>,
+    id: NodeId,
+    props: &Props,
+    outputs: &mut HashMap<NodeId, Vec<port::GVal>>,
+    visiting: &mut Vec<NodeId>,
+) -> Result<Vec<port::GVal>, DmGuiError> {
+    if let Some(vals) = outputs.get(&id) {
+        return Ok(vals.clone());
+    }
+    let n = &graph.nodes[id];
+    if visiting.contains(&id) {
+        return Err(DmGuiError::evaluation(format!("{} is part of a cycle", n.label)));
+    }
+    let f = match driver(&n.user_data) {
+        Driver::Func(f) => f,
+        _ => return Err(DmGuiError::evaluation(format!("{} has no values to connect", n.label))),
+    };
+    visiting.push(id);
+    let vals = input_values(graph, id, props, outputs, visiting).and_then(|inp| f(props, inp));
+    visiting.pop();
+    let vals = vals?;
+    outputs.insert(id, vals.clone());
+    Ok(vals)
 }
@@ -0,0 +1,29 @@
+use crate::{
+    app_graph::GraphClip,
+    dmx::{Fixture, FixtureType},
+    error::DmGuiError,
+};
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// Content exchanged through the system clipboard as json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "tronomic", content = "data")]
+pub enum Clip {
+    Nodes(GraphClip),
+    /// Fixtures with the types they use, so they can be pasted into a show missing those types
+    Fixtures {
+        types: HashMap<usize, FixtureType>,
+        fixts: Vec<Fixture>,
+    },
+}
+
+impl Clip {
+    pub fn to_json(&self) -> Result<String, DmGuiError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, DmGuiError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
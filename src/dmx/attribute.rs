@@ -0,0 +1,79 @@
+use super::{ChannelPurpose, ShutterState};
+use crate::color::Rgba;
+use serde::{Deserialize, Serialize};
+
+/// A value of a fixture independent of its channel layout.
+/// Intensity is normalized (0..=1), pan and tilt are in degrees around the center position.
+/// Capabilities are selected by name, optionally with a physical value inside of the capability.
+/// A point is given in stage coordinates, see [`super::Placement`].
+/// The shutter is mapped through the shutter capabilities of the fixture type, see [`ShutterState`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Attribute {
+    Intensity(f64),
+    Color(Rgba),
+    Pan(f64),
+    Tilt(f64),
+    Point([f64; 3]),
+    Shutter(ShutterState),
+    Capability {
+        purpose: ChannelPurpose,
+        name: String,
+        physical: Option<f64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AttributeKind {
+    Intensity,
+    Color,
+    Pan,
+    Tilt,
+    Point,
+    Shutter,
+    Capability(ChannelPurpose),
+}
+
+impl Attribute {
+    pub fn kind(&self) -> AttributeKind {
+        match self {
+            Self::Intensity(_) => AttributeKind::Intensity,
+            Self::Color(_) => AttributeKind::Color,
+            Self::Pan(_) => AttributeKind::Pan,
+            Self::Tilt(_) => AttributeKind::Tilt,
+            Self::Point(_) => AttributeKind::Point,
+            Self::Shutter(_) => AttributeKind::Shutter,
+            Self::Capability { purpose, .. } => AttributeKind::Capability(*purpose),
+        }
+    }
+}
+
+impl AttributeKind {
+    /// Coarse channel purposes an attribute of this kind is written to
+    pub fn purposes(&self) -> Vec<ChannelPurpose> {
+        use ChannelPurpose::*;
+        match self {
+            Self::Intensity => vec![Dimmer],
+            Self::Color => vec![ColR, ColG, ColB, ColW, ColA, ColUv, ColL, ColC, ColM, ColY, ColK],
+            Self::Pan => vec![Pan],
+            Self::Tilt => vec![Tilt],
+            Self::Point => vec![Pan, Tilt],
+            Self::Shutter => vec![Shutter, Strobe],
+            Self::Capability(p) => vec![*p],
+        }
+    }
+}
+
+impl ChannelPurpose {
+    /// Attribute a channel of this purpose belongs to, fine channels included
+    pub fn attribute(&self) -> Option<AttributeKind> {
+        use ChannelPurpose::*;
+        match self {
+            Dimmer | DimmerFine => Some(AttributeKind::Intensity),
+            ColR | ColG | ColB | ColW | ColA | ColUv | ColL | ColC | ColM | ColY | ColK => Some(AttributeKind::Color),
+            Pan | PanFine | PanUltra => Some(AttributeKind::Pan),
+            Tilt | TiltFine | TiltUltra => Some(AttributeKind::Tilt),
+            Shutter | Strobe => Some(AttributeKind::Shutter),
+            _ => None,
+        }
+    }
+}
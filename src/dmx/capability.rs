@@ -0,0 +1,128 @@
+use crate::color::Rgb;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PhysicalUnit {
+    Percent,
+    Hertz,
+    Degrees,
+    DegreesPerSecond,
+    Seconds,
+    Kelvin,
+}
+
+impl PhysicalUnit {
+    pub const ALL: &'static [PhysicalUnit] = &[
+        Self::Percent,
+        Self::Hertz,
+        Self::Degrees,
+        Self::DegreesPerSecond,
+        Self::Seconds,
+        Self::Kelvin,
+    ];
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Self::Percent => "%",
+            Self::Hertz => "Hz",
+            Self::Degrees => "°",
+            Self::DegreesPerSecond => "°/s",
+            Self::Seconds => "s",
+            Self::Kelvin => "K",
+        }
+    }
+}
+
+/// Physical quantity a capability covers, interpolated linearly over its DMX range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Physical {
+    pub unit: PhysicalUnit,
+    pub from: f64,
+    pub to: f64,
+}
+
+/// Meaning of a range of (coarse) DMX values of a channel, e.g. "Gobo 3" or "Strobe slow to fast"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Capability {
+    pub name: String,
+    pub range: RangeInclusive<u8>,
+    pub physical: Option<Physical>,
+    /// Approximate colour of a colour wheel slot, two for a split position showing both halves
+    #[serde(default)]
+    pub colors: Vec<Rgb>,
+}
+
+impl Capability {
+    pub fn new<S: Into<String>>(name: S, range: RangeInclusive<u8>) -> Self {
+        Self {
+            name: name.into(),
+            range,
+            physical: None,
+            colors: Vec::new(),
+        }
+    }
+
+    pub fn with_physical(mut self, unit: PhysicalUnit, from: f64, to: f64) -> Self {
+        self.physical = Some(Physical { unit, from, to });
+        self
+    }
+
+    pub fn with_colors(mut self, colors: Vec<[f64; 3]>) -> Self {
+        self.colors = colors.into_iter().map(Rgb).collect();
+        self
+    }
+
+    /// Colour of the beam at this capability, split positions give the mix of both halves
+    pub fn color(&self) -> Option<Rgb> {
+        if self.colors.is_empty() {
+            return None;
+        }
+        let n = self.colors.len() as f64;
+        let mut sum = [0.; 3];
+        for c in &self.colors {
+            for (s, v) in sum.iter_mut().zip(c.0) {
+                *s += v / n;
+            }
+        }
+        Some(Rgb(sum))
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.colors.len() > 1
+    }
+
+    pub fn contains(&self, v: u8) -> bool {
+        self.range.contains(&v)
+    }
+
+    /// DMX value selecting this capability
+    pub fn value(&self) -> u8 {
+        *self.range.start()
+    }
+
+    /// DMX value for a physical value, clamped to the range of the capability.
+    /// Capabilities without physical values always give their start value.
+    pub fn value_for(&self, physical: f64) -> u8 {
+        let (start, end) = (*self.range.start() as f64, *self.range.end() as f64);
+        match &self.physical {
+            Some(p) if p.from != p.to => {
+                let t = ((physical - p.from) / (p.to - p.from)).clamp(0., 1.);
+                (start + t * (end - start)).round() as u8
+            }
+            _ => self.value(),
+        }
+    }
+
+    /// Physical value at a DMX value inside of the range
+    pub fn physical_at(&self, v: u8) -> Option<f64> {
+        let p = self.physical.as_ref()?;
+        let (start, end) = (*self.range.start() as f64, *self.range.end() as f64);
+        let t = if end > start {
+            ((v as f64 - start) / (end - start)).clamp(0., 1.)
+        } else {
+            0.
+        };
+        Some(p.from + t * (p.to - p.from))
+    }
+}
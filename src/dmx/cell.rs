@@ -0,0 +1,140 @@
+use super::{AttributeKind, ChannelPurpose, DmxState, Fixture, FixtureMode};
+use serde::{Deserialize, Serialize};
+
+/// One of many identical parts of a fixture, like a pixel of an LED bar or a head of a multi-head
+/// fixture. Patched fixtures get a fixture for each of their cells, so cells can be grouped and
+/// controlled like any other fixture while the parent keeps the channels shared by all cells.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cell {
+    /// First channel of the cell relative to the start of the fixture
+    pub offset: usize,
+    /// Position of the cell within the fixture, x to the right and y down, in cells
+    pub position: [f64; 2],
+    /// Channel layout of the cell from `offset` on, its name is the name of the cell
+    pub mode: FixtureMode,
+}
+
+impl Cell {
+    pub fn new<S: Into<String>>(name: S, offset: usize, channel_purposes: Vec<ChannelPurpose>) -> Self {
+        Self {
+            offset,
+            position: [0.; 2],
+            mode: FixtureMode::new(name, channel_purposes),
+        }
+    }
+
+    pub fn with_position(mut self, x: f64, y: f64) -> Self {
+        self.position = [x, y];
+        self
+    }
+
+    /// Offset behind the last channel of the cell
+    pub fn end(&self) -> usize {
+        self.offset + self.mode.footprint()
+    }
+}
+
+/// `count` cells with the same layout following each other from `offset` on, laid out in rows
+/// of `columns` cells
+pub fn cell_row(count: usize, columns: usize, offset: usize, channel_purposes: &[ChannelPurpose]) -> Vec<Cell> {
+    let columns = columns.max(1);
+    (0..count)
+        .map(|i| {
+            Cell::new(format!("Cell {}", i + 1), offset + i * channel_purposes.len(), channel_purposes.to_vec())
+                .with_position((i % columns) as f64, (i / columns) as f64)
+        })
+        .collect()
+}
+
+impl DmxState {
+    /// Creates, updates and removes the cell fixtures of a fixture to match its mode. Cells keep
+    /// their ids as long as the parent lists them, so groups of cells survive re-patching.
+    pub fn sync_cells(&mut self, fixt_id: usize) {
+        let parent = match self.fixts.get(&fixt_id) {
+            Some(f) if f.cell_of.is_none() => f.clone(),
+            _ => return,
+        };
+        let cells = self
+            .library
+            .mode(parent.type_id, parent.mode)
+            .map(|m| m.cells.clone())
+            .unwrap_or_default();
+
+        for id in parent.cells.iter().skip(cells.len()) {
+            if self.fixts.get(id).is_some_and(|f| f.cell_of.map(|(p, _)| p) == Some(fixt_id)) {
+                self.fixts.remove(id);
+            }
+        }
+        let mut ids = Vec::with_capacity(cells.len());
+        for (i, cell) in cells.iter().enumerate() {
+            let existing = parent
+                .cells
+                .get(i)
+                .filter(|id| self.fixts.get(id).is_none_or(|f| f.cell_of == Some((fixt_id, i))))
+                .copied();
+            let id = existing.unwrap_or_else(|| {
+                self.fixt_next_id += 1;
+                self.fixt_next_id - 1
+            });
+            let new = Fixture {
+                name: format!("{} {}", parent.name, cell.mode.name),
+                start: parent.start + cell.offset,
+                curves: self.fixts.get(&id).map(|f| f.curves.clone()).unwrap_or_default(),
+                placement: None,
+                cell_of: Some((fixt_id, i)),
+                cells: Vec::new(),
+                ..parent.clone()
+            };
+            if self.fixts.get(&id) != Some(&new) {
+                self.fixt_next_id = self.fixt_next_id.max(id + 1);
+                self.fixts.insert(id, new);
+            }
+            ids.push(id);
+        }
+        if parent.cells != ids {
+            self.fixts.get_mut(&fixt_id).unwrap().cells = ids;
+        }
+    }
+
+    /// Brings the cells of all fixtures in line with the library, e.g. after it was edited
+    pub fn sync_all_cells(&mut self) {
+        let parents: Vec<usize> = self
+            .fixts
+            .iter()
+            .filter(|(_, f)| f.cell_of.is_none())
+            .map(|(id, _)| *id)
+            .collect();
+        for id in parents {
+            self.sync_cells(id);
+        }
+        // Cells whose parent is gone
+        let cells: Vec<usize> = self.fixts.values().flat_map(|f| f.cells.clone()).collect();
+        self.fixts.retain(|id, f| f.cell_of.is_none() || cells.contains(id));
+    }
+
+    /// Cells an attribute of a fixture is passed on to, as its own channels don't provide it.
+    /// Empty for fixtures without cells.
+    pub fn attr_cells(&self, fixt_id: usize, kind: AttributeKind) -> Vec<usize> {
+        let fixt = match self.fixts.get(&fixt_id) {
+            Some(f) if !f.cells.is_empty() => f,
+            _ => return Vec::new(),
+        };
+        let own = self.fixture_mode(fixt).is_some_and(|mode| {
+            kind.purposes().iter().any(|p| mode.offset_of(*p).is_some())
+                || (kind == AttributeKind::Color && mode.offset_of(ChannelPurpose::ColorWheel).is_some())
+        });
+        match own {
+            true => Vec::new(),
+            false => fixt.cells.clone(),
+        }
+    }
+
+    /// Parent of a cell if the parent has a dimmer of its own, which then dims the cell as well
+    pub fn dimming_parent(&self, fixt_id: usize) -> Option<usize> {
+        let (parent_id, _) = self.fixts.get(&fixt_id)?.cell_of?;
+        let parent = self.fixts.get(&parent_id)?;
+        self.fixture_mode(parent)?
+            .offset_of(ChannelPurpose::Dimmer)
+            .map(|_| parent_id)
+    }
+}
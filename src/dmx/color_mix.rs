@@ -0,0 +1,199 @@
+use super::{Capability, ChannelPurpose};
+use crate::color::{Rgb, Rgba};
+use serde::{Deserialize, Serialize};
+
+/// Emitter next to red, green and blue, e.g. white or amber. It takes over the part of a colour it
+/// is able to produce from the primaries.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Emitter {
+    pub purpose: ChannelPurpose,
+    /// Colour of the emitter at full, relative to full red, green and blue
+    pub color: Rgb,
+    /// Share of the producible part actually moved to this emitter (0..=1)
+    pub extraction: f64,
+}
+
+impl Emitter {
+    pub fn new(purpose: ChannelPurpose, color: [f64; 3]) -> Self {
+        Self {
+            purpose,
+            color: Rgb(color),
+            extraction: 1.,
+        }
+    }
+}
+
+/// Converts colours to the emitter levels of a fixture type. Fixtures with red, green, blue or
+/// one of the emitters mix additively, the emitters are extracted in their order first. Fixtures
+/// with only cyan, magenta and yellow (and black) mix subtractively. Fixtures without any of
+/// these use the nearest slot of their colour wheel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorMixing {
+    pub emitters: Vec<Emitter>,
+    /// Colour wheel positions between two slots may be picked as well
+    #[serde(default)]
+    pub wheel_splits: bool,
+}
+
+impl Default for ColorMixing {
+    fn default() -> Self {
+        use ChannelPurpose::*;
+        Self {
+            emitters: vec![
+                Emitter::new(ColW, [1., 1., 1.]),
+                Emitter::new(ColA, [1., 0.5, 0.]),
+                Emitter::new(ColL, [0.6, 1., 0.]),
+            ],
+            wheel_splits: false,
+        }
+    }
+}
+
+impl ColorMixing {
+    /// Levels (0..=1) for the colour channels among `available` producing `color`
+    pub fn mix(&self, color: Rgba, available: &[ChannelPurpose]) -> Vec<(ChannelPurpose, f64)> {
+        use ChannelPurpose::*;
+        let [r, g, b, _] = color.0;
+        let mut rem = [r, g, b].map(|c| c.clamp(0., 1.));
+        let has = |p: &ChannelPurpose| available.contains(p);
+
+        if [ColR, ColG, ColB].iter().any(has) || self.emitters.iter().any(|e| has(&e.purpose)) {
+            let mut out = Vec::new();
+            for e in self.emitters.iter().filter(|e| has(&e.purpose)) {
+                let level = (0..3)
+                    .filter(|i| e.color.0[*i] > 0.)
+                    .map(|i| rem[i] / e.color.0[i])
+                    .fold(f64::INFINITY, f64::min);
+                let level = match level.is_finite() {
+                    true => level.clamp(0., 1.) * e.extraction.clamp(0., 1.),
+                    false => 0.,
+                };
+                for (c, ec) in rem.iter_mut().zip(e.color.0) {
+                    *c = (*c - level * ec).max(0.);
+                }
+                out.push((e.purpose, level));
+            }
+            out.extend(
+                [(ColR, rem[0]), (ColG, rem[1]), (ColB, rem[2])]
+                    .into_iter()
+                    .filter(|(p, _)| has(p)),
+            );
+            out
+        } else if has(&ColK) {
+            let k = 1. - rem.iter().copied().fold(0., f64::max);
+            let sub = |c: f64| if k < 1. { (1. - c - k) / (1. - k) } else { 0. };
+            [(ColC, sub(rem[0])), (ColM, sub(rem[1])), (ColY, sub(rem[2])), (ColK, k)]
+                .into_iter()
+                .filter(|(p, _)| has(p))
+                .collect()
+        } else {
+            [(ColC, 1. - rem[0]), (ColM, 1. - rem[1]), (ColY, 1. - rem[2])]
+                .into_iter()
+                .filter(|(p, _)| has(p))
+                .collect()
+        }
+    }
+
+    /// Colour wheel slot closest to the hue and saturation of `color`, brightness is ignored as a
+    /// wheel can't dim
+    pub fn nearest_slot<'a>(&self, slots: &'a [Capability], color: Rgba) -> Option<&'a Capability> {
+        let [r, g, b, _] = color.0;
+        let target = normalize([r, g, b]);
+        slots
+            .iter()
+            .filter(|c| self.wheel_splits || !c.is_split())
+            .filter_map(|c| Some((c, normalize(c.color()?.0))))
+            .min_by(|(_, a), (_, b)| distance(*a, target).total_cmp(&distance(*b, target)))
+            .map(|(c, _)| c)
+    }
+
+    /// Colour produced by emitter levels, the inverse of [`ColorMixing::mix`]. `None` if there
+    /// are no colour channels.
+    pub fn unmix(&self, levels: &[(ChannelPurpose, f64)]) -> Option<Rgba> {
+        use ChannelPurpose::*;
+        let level = |p: ChannelPurpose| levels.iter().find(|(l, _)| *l == p).map(|(_, v)| *v);
+        let mut rgb = [0.; 3];
+        let mut any = false;
+
+        let mut additive = [ColR, ColG, ColB].into_iter().chain(self.emitters.iter().map(|e| e.purpose));
+        if additive.any(|p| level(p).is_some()) {
+            for (i, p) in [ColR, ColG, ColB].into_iter().enumerate() {
+                rgb[i] = level(p).unwrap_or(0.);
+            }
+            for e in &self.emitters {
+                if let Some(v) = level(e.purpose) {
+                    for (c, ec) in rgb.iter_mut().zip(e.color.0) {
+                        *c += v * ec;
+                    }
+                }
+            }
+            any = true;
+        } else {
+            let k = level(ColK).unwrap_or(0.);
+            for (i, p) in [ColC, ColM, ColY].into_iter().enumerate() {
+                any |= level(p).is_some();
+                rgb[i] = (1. - level(p).unwrap_or(0.)) * (1. - k);
+            }
+        }
+        any.then(|| Rgba([rgb[0].min(1.), rgb[1].min(1.), rgb[2].min(1.), 1.]))
+    }
+}
+
+/// Scales a colour to full brightness
+fn normalize(c: [f64; 3]) -> [f64; 3] {
+    let max = c.iter().copied().fold(0., f64::max);
+    if max > 0. {
+        c.map(|v| v / max)
+    } else {
+        c
+    }
+}
+
+fn distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ChannelPurpose::*;
+
+    fn close(a: Rgba, b: [f64; 3]) -> bool {
+        (0..3).all(|i| (a.0[i] - b[i]).abs() < 1e-9)
+    }
+
+    #[test]
+    fn emitters_take_over_their_part() {
+        let mixing = ColorMixing::default();
+        let levels = mixing.mix(Rgba([1., 1., 1., 1.]), &[ColR, ColG, ColB, ColW]);
+        assert_eq!(levels, vec![(ColW, 1.), (ColR, 0.), (ColG, 0.), (ColB, 0.)]);
+        let levels = mixing.mix(Rgba([1., 0.5, 0., 1.]), &[ColR, ColG, ColB, ColA]);
+        assert_eq!(levels, vec![(ColA, 1.), (ColR, 0.), (ColG, 0.), (ColB, 0.)]);
+
+        let uv = ColorMixing {
+            emitters: vec![Emitter::new(ColUv, [0.3, 0., 1.])],
+            wheel_splits: false,
+        };
+        let levels = uv.mix(Rgba([0.3, 0., 1., 1.]), &[ColUv]);
+        assert_eq!(levels, vec![(ColUv, 1.)]);
+    }
+
+    #[test]
+    fn unmix_inverts_mix() {
+        let mixing = ColorMixing::default();
+        for layout in [
+            vec![ColR, ColG, ColB],
+            vec![ColR, ColG, ColB, ColW],
+            vec![ColR, ColG, ColB, ColW, ColA, ColL],
+            vec![ColC, ColM, ColY],
+            vec![ColC, ColM, ColY, ColK],
+        ] {
+            for color in [[1., 1., 1.], [0.2, 0.4, 0.9], [1., 0.5, 0.], [0., 0., 0.]] {
+                let levels = mixing.mix(Rgba([color[0], color[1], color[2], 1.]), &layout);
+                let back = mixing.unmix(&levels).unwrap();
+                assert!(close(back, color), "{layout:?}: {color:?} came back as {back:?}");
+            }
+        }
+        assert_eq!(mixing.unmix(&[(Dimmer, 1.)]), None);
+    }
+}
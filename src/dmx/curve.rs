@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+pub const LUT_SMALL: usize = 256;
+pub const LUT_LARGE: usize = 65536;
+
+/// Response curve applied to a channel between the merge and the output
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// Slow start, for LED fixtures looking too bright at the bottom of a fade
+    SquareLaw,
+    Smoothstep,
+    /// Fast start
+    InverseSquare,
+    /// Output values (0..=u16::MAX) for evenly spaced inputs, interpolated in between.
+    /// Usually 256 or 65536 entries.
+    Lut(Vec<u16>),
+}
+
+impl Curve {
+    pub const PRESETS: &'static [Curve] = &[Self::Linear, Self::SquareLaw, Self::Smoothstep, Self::InverseSquare];
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Linear => "Linear".into(),
+            Self::SquareLaw => "Square law".into(),
+            Self::Smoothstep => "S-curve".into(),
+            Self::InverseSquare => "Inverse square".into(),
+            Self::Lut(lut) => format!("LUT ({} entries)", lut.len()),
+        }
+    }
+
+    /// Maps a normalized value (0..=1) through the curve
+    pub fn apply(&self, v: f64) -> f64 {
+        let v = v.clamp(0., 1.);
+        match self {
+            Self::Linear => v,
+            Self::SquareLaw => v * v,
+            Self::Smoothstep => v * v * (3. - 2. * v),
+            Self::InverseSquare => v.sqrt(),
+            Self::Lut(lut) => sample_lut(lut, v),
+        }
+    }
+
+    /// Lookup table with `len` entries following this curve, used as the start for drawing one
+    pub fn to_lut(&self, len: usize) -> Vec<u16> {
+        let len = len.max(2);
+        (0..len)
+            .map(|i| (self.apply(i as f64 / (len - 1) as f64) * u16::MAX as f64).round() as u16)
+            .collect()
+    }
+
+    pub fn is_linear(&self) -> bool {
+        matches!(self, Self::Linear)
+    }
+}
+
+/// Normalized output of a lookup table at `v` (0..=1), interpolating between the entries
+pub fn sample_lut(lut: &[u16], v: f64) -> f64 {
+    let v = v.clamp(0., 1.);
+    match lut.len() {
+        0 => v,
+        1 => lut[0] as f64 / u16::MAX as f64,
+        len => {
+            let pos = v * (len - 1) as f64;
+            let (i, t) = (pos.floor() as usize, pos.fract());
+            let a = lut[i] as f64;
+            let b = lut[(i + 1).min(len - 1)] as f64;
+            (a + (b - a) * t) / u16::MAX as f64
+        }
+    }
+}
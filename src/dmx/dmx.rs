@@ -1,13 +1,39 @@
 use std::collections::HashMap;
-use super::{Universe, Fixture};
+use super::{Attribute, AttributeKind, Capability, ChannelPurpose, ColorMixing, Fixture, FixtureGroup, FixtureLibrary, FixtureMode, FixturePurpose};
+use super::Target;
+use super::{ChannelModifier, Highlight, Masters, Modifiers, PixelMap, SafetyLimiter, Source, Universe, UniverseMeta, UNIVERSE_SIZE};
+use super::{ImageLoader, VirtualIntensities};
+use super::{combine_value, degrees_to_value, split_value, value_to_degrees};
+use crate::error::DmGuiError;
 
 
+/// Everything removed with a universe, see [`DmxState::set_universe`]
+#[derive(Debug, Clone)]
+pub struct UniverseSnapshot {
+    pub universe: Universe,
+    pub modifiers: HashMap<usize, ChannelModifier>,
+    pub parked: HashMap<usize, u8>,
+}
+
 #[derive(Debug, Clone)]
 pub struct DmxState {
     pub universes: HashMap<usize, Universe>,
+    pub library: FixtureLibrary,
     pub fixt_next_id: usize,
     pub fixts: HashMap<usize, Fixture>,
-    pub fixt_groups: HashMap<usize, Vec<usize>>,
+    pub group_next_id: usize,
+    pub fixt_groups: HashMap<usize, FixtureGroup>,
+    pub modifiers: Modifiers,
+    pub masters: Masters,
+    /// Intensity of fixtures without a dimmer, see [`DmxState::virtual_intensity`]
+    pub virtual_intensity: VirtualIntensities,
+    pub highlight: Highlight,
+    pub safety: SafetyLimiter,
+    /// Frames of the universes as last sent, after the safety limiter
+    pub sent_frames: HashMap<usize, Vec<u8>>,
+    pub pixel_maps: Vec<PixelMap>,
+    /// Frames of the image files played by the pixel maps
+    pub images: ImageLoader,
 }
 
 impl DmxState {
@@ -16,23 +42,455 @@ impl DmxState {
             universes: {
                 let mut n = HashMap::new();
                 for i in 0..universe_count {
-                    n.insert(i, Universe::new());
+                    n.insert(i, Universe::new(UniverseMeta::new(format!("Universe {}", i + 1))));
                 }
                 n
             },
+            library: FixtureLibrary::default(),
             fixts: HashMap::new(),
             fixt_next_id: 0,
+            group_next_id: 0,
             fixt_groups: HashMap::new(),
+            modifiers: Modifiers::default(),
+            masters: Masters::default(),
+            virtual_intensity: VirtualIntensities::default(),
+            highlight: Highlight::default(),
+            safety: SafetyLimiter::default(),
+            sent_frames: HashMap::new(),
+            pixel_maps: Vec::new(),
+            images: ImageLoader::default(),
+        }
+    }
+
+    /// Adds a universe behind the highest id in use and returns its id
+    pub fn add_universe(&mut self, meta: UniverseMeta) -> usize {
+        let new_id = self.universes.keys().map(|id| id + 1).max().unwrap_or(0);
+        self.universes.insert(new_id, Universe::new(meta));
+        new_id
+    }
+
+    /// Removes a universe, fixtures patched to it stay patched and show up as patch conflicts
+    pub fn remove_universe(&mut self, id: usize) -> Option<UniverseSnapshot> {
+        self.set_universe(id, None)
+    }
+
+    /// A universe together with its channel modifiers and parked channels
+    pub fn universe_snapshot(&self, id: usize) -> Option<UniverseSnapshot> {
+        Some(UniverseSnapshot {
+            universe: self.universes.get(&id)?.clone(),
+            modifiers: self.modifiers.channels.get(&id).cloned().unwrap_or_default(),
+            parked: self.modifiers.parked.get(&id).cloned().unwrap_or_default(),
+        })
+    }
+
+    /// Inserts, replaces or removes a universe with its channel modifiers and parked channels
+    pub fn set_universe(&mut self, id: usize, snapshot: Option<UniverseSnapshot>) -> Option<UniverseSnapshot> {
+        let old = self.universe_snapshot(id);
+        self.universes.remove(&id);
+        self.modifiers.channels.remove(&id);
+        self.modifiers.parked.remove(&id);
+        if let Some(snapshot) = snapshot {
+            self.universes.insert(id, snapshot.universe);
+            if !snapshot.modifiers.is_empty() {
+                self.modifiers.channels.insert(id, snapshot.modifiers);
+            }
+            if !snapshot.parked.is_empty() {
+                self.modifiers.parked.insert(id, snapshot.parked);
+            }
         }
+        old
+    }
+
+    /// Universe ids in ascending order
+    pub fn universe_ids(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.universes.keys().copied().collect();
+        ids.sort_unstable();
+        ids
     }
 
+    /// Patches a fixture, fixtures with cells get one for each cell as well
     pub fn add_fixture(&mut self, new: Fixture) -> usize {
         let new_id = self.fixt_next_id;
         self.fixt_next_id += 1;
         self.fixts.insert(new_id, new);
+        self.sync_cells(new_id);
         new_id
     }
     pub fn remove_fixture(&mut self, id: usize) -> Option<Fixture> {
-        self.fixts.remove(&id)
+        self.set_fixture(id, None)
+    }
+
+    /// Fixture number following the highest one in use
+    pub fn next_fixture_number(&self) -> usize {
+        self.fixts.values().map(|f| f.number + 1).max().unwrap_or(1)
+    }
+
+    /// Channel layout of a fixture, the one of its cell for cell fixtures
+    pub fn fixture_mode(&self, fixt: &Fixture) -> Option<&FixtureMode> {
+        let mode = self.library.mode(fixt.type_id, fixt.mode)?;
+        match fixt.cell_of {
+            Some((_, cell)) => mode.cells.get(cell).map(|c| &c.mode),
+            None => Some(mode),
+        }
+    }
+
+    /// Number of channels a fixture occupies, 0 if its type or mode doesn't exist
+    pub fn footprint(&self, fixt: &Fixture) -> usize {
+        self.fixture_mode(fixt).map(|m| m.footprint()).unwrap_or(0)
+    }
+
+    fn layout(&self, fixt_id: usize) -> Result<(&Fixture, &FixtureMode), DmGuiError> {
+        let fixt = self
+            .fixts
+            .get(&fixt_id)
+            .ok_or_else(|| DmGuiError::dmx(format!("fixture {fixt_id} does not exist")))?;
+        let mode = self.fixture_mode(fixt).ok_or_else(|| {
+            DmGuiError::dmx(format!(
+                "mode {} of fixture type {} used by fixture {fixt_id} does not exist",
+                fixt.mode, fixt.type_id
+            ))
+        })?;
+        Ok((fixt, mode))
+    }
+
+    /// Inserts, replaces or removes a fixture together with its cells
+    pub fn set_fixture(&mut self, id: usize, fixt: Option<Fixture>) -> Option<Fixture> {
+        match fixt {
+            Some(f) => {
+                self.fixt_next_id = self.fixt_next_id.max(id + 1);
+                let old = self.fixts.insert(id, f);
+                self.sync_cells(id);
+                old
+            }
+            None => {
+                let old = self.fixts.remove(&id)?;
+                for cell in &old.cells {
+                    self.fixts.remove(cell);
+                }
+                Some(old)
+            }
+        }
+    }
+
+    /// First address in `universe_id` with `footprint` unused channels, searching from the start.
+    pub fn next_free_address(&self, universe_id: usize, footprint: usize) -> Option<usize> {
+        self.free_address_from(universe_id, 0, footprint, &[])
+    }
+
+    /// Writes a normalized value (0..=1) to `purpose` of a fixture, split across all of its fine
+    /// channels.
+    pub fn set_fixture_value(
+        &mut self,
+        src: Source,
+        fixt_id: usize,
+        purpose: ChannelPurpose,
+        v: f64,
+    ) -> Result<(), DmGuiError> {
+        let (fixt, mode) = self.layout(fixt_id)?;
+        let offsets = mode.value_offsets(purpose);
+        if offsets.is_empty() {
+            return Err(DmGuiError::dmx(format!(
+                "fixture {fixt_id} has no channel for {purpose:?}"
+            )));
+        }
+        let (universe_id, start) = (fixt.universe_id, fixt.start);
+        let un = self.universes.get_mut(&universe_id).ok_or_else(|| {
+            DmGuiError::dmx(format!("universe {universe_id} of fixture {fixt_id} does not exist"))
+        })?;
+
+        if let Some(ch) = offsets.iter().map(|o| start + o).find(|ch| *ch >= UNIVERSE_SIZE) {
+            return Err(DmGuiError::dmx(format!(
+                "channel {ch} of fixture {fixt_id} is outside of its universe"
+            )));
+        }
+        let bytes = split_value(v, offsets.len());
+        for (o, byte) in offsets.into_iter().zip(bytes) {
+            un.set_source(src, start + o, byte);
+        }
+        Ok(())
+    }
+
+    /// Reads the merged value of `purpose` of a fixture combined from all of its fine channels
+    pub fn fixture_value(&self, fixt_id: usize, purpose: ChannelPurpose) -> Option<f64> {
+        let (fixt, mode) = self.layout(fixt_id).ok()?;
+        let un = self.universes.get(&fixt.universe_id)?;
+        let vals = mode
+            .value_offsets(purpose)
+            .into_iter()
+            .map(|o| (fixt.start + o < UNIVERSE_SIZE).then(|| un.get(fixt.start + o)))
+            .collect::<Option<Vec<u8>>>()?;
+        (!vals.is_empty()).then(|| combine_value(&vals))
+    }
+
+    /// Writes raw values to the channels of a fixture, starting at its first one
+    pub fn set_fixture_channels(&mut self, src: Source, fixt_id: usize, vals: &[u8]) -> Result<(), DmGuiError> {
+        let (fixt, _) = self.layout(fixt_id)?;
+        let (universe_id, start) = (fixt.universe_id, fixt.start);
+        let un = self.universes.get_mut(&universe_id).ok_or_else(|| {
+            DmGuiError::dmx(format!("universe {universe_id} of fixture {fixt_id} does not exist"))
+        })?;
+        if start + vals.len() > UNIVERSE_SIZE {
+            return Err(DmGuiError::dmx(format!(
+                "fixture {fixt_id} does not fit into its universe"
+            )));
+        }
+        for (o, v) in vals.iter().enumerate() {
+            un.set_source(src, start + o, *v);
+        }
+        Ok(())
+    }
+
+    /// Writes the home value of every channel of a fixture and its cells
+    pub fn home_fixture(&mut self, src: Source, fixt_id: usize) -> Result<(), DmGuiError> {
+        let (_, mode) = self.layout(fixt_id)?;
+        let vals: Vec<u8> = mode
+            .channel_purposes
+            .iter()
+            .map(|p| mode.channel_defaults(*p).home)
+            .collect();
+        let cells = self.fixts[&fixt_id].cells.clone();
+        self.set_fixture_channels(src, fixt_id, &vals)?;
+        if self.virtual_intensity(fixt_id).is_some() {
+            self.set_virtual_intensity(src, fixt_id, 0.)?;
+        }
+        for cell in cells {
+            self.home_fixture(src, cell)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a raw DMX value to the coarse channel of `purpose`, its fine channels are set to 0
+    pub fn set_fixture_raw(
+        &mut self,
+        src: Source,
+        fixt_id: usize,
+        purpose: ChannelPurpose,
+        v: u8,
+    ) -> Result<(), DmGuiError> {
+        let (_, mode) = self.layout(fixt_id)?;
+        let bytes = mode.value_offsets(purpose).len().max(1);
+        let max = (1u64 << (8 * bytes)) - 1;
+        let raw = ((v as u64) << (8 * (bytes - 1))) as f64 / max as f64;
+        self.set_fixture_value(src, fixt_id, purpose, raw)
+    }
+
+    /// Merged value of the coarse channel of `purpose`
+    pub fn fixture_raw(&self, fixt_id: usize, purpose: ChannelPurpose) -> Option<u8> {
+        let (fixt, mode) = self.layout(fixt_id).ok()?;
+        let ch = fixt.start + mode.offset_of(purpose)?;
+        let un = self.universes.get(&fixt.universe_id)?;
+        (ch < UNIVERSE_SIZE).then(|| un.get(ch))
+    }
+
+    /// Selects a capability of a channel by name. With a physical value the DMX value is picked
+    /// inside of the capability range, otherwise its start value is used.
+    pub fn set_capability(
+        &mut self,
+        src: Source,
+        fixt_id: usize,
+        purpose: ChannelPurpose,
+        name: &str,
+        physical: Option<f64>,
+    ) -> Result<(), DmGuiError> {
+        let (_, mode) = self.layout(fixt_id)?;
+        let cap = mode.capability(purpose, name).ok_or_else(|| {
+            DmGuiError::dmx(format!("fixture {fixt_id} has no capability {name} on {purpose:?}"))
+        })?;
+        let v = match physical {
+            Some(p) => cap.value_for(p),
+            None => cap.value(),
+        };
+        self.set_fixture_raw(src, fixt_id, purpose, v)
+    }
+
+    /// Capability of a channel selected by the merged value
+    pub fn capability(&self, fixt_id: usize, purpose: ChannelPurpose) -> Option<&Capability> {
+        let (_, mode) = self.layout(fixt_id).ok()?;
+        mode.capability_at(purpose, self.fixture_raw(fixt_id, purpose)?)
+    }
+
+    pub fn set_fixture_degrees(
+        &mut self,
+        src: Source,
+        fixt_id: usize,
+        purpose: ChannelPurpose,
+        deg: f64,
+    ) -> Result<(), DmGuiError> {
+        let (fixt, _) = self.layout(fixt_id)?;
+        let range = self
+            .degree_range(fixt, purpose)
+            .ok_or_else(|| DmGuiError::dmx(format!("{purpose:?} is not an angle")))?;
+        self.set_fixture_value(src, fixt_id, purpose, degrees_to_value(deg, range))
+    }
+
+    pub fn fixture_degrees(&self, fixt_id: usize, purpose: ChannelPurpose) -> Option<f64> {
+        let range = self.degree_range(self.layout(fixt_id).ok()?.0, purpose)?;
+        Some(value_to_degrees(self.fixture_value(fixt_id, purpose)?, range))
+    }
+
+    /// Colour mixing of the type of a fixture
+    pub fn color_mixing(&self, fixt: &Fixture) -> ColorMixing {
+        self.library
+            .types
+            .get(&fixt.type_id)
+            .map(|t| t.color_mixing.clone())
+            .unwrap_or_default()
+    }
+
+    /// Writes an attribute of a fixture to the channels its layout provides for it. Attributes
+    /// only the cells of a fixture provide are written to all of its cells.
+    pub fn set_attr(&mut self, src: Source, fixt_id: usize, attr: Attribute) -> Result<(), DmGuiError> {
+        use ChannelPurpose::*;
+        let cells = self.attr_cells(fixt_id, attr.kind());
+        if !cells.is_empty() {
+            for cell in cells {
+                self.set_attr(src, cell, attr.clone())?;
+            }
+            return Ok(());
+        }
+        match attr {
+            Attribute::Intensity(v) => match self.virtual_intensity(fixt_id) {
+                Some(_) => self.set_virtual_intensity(src, fixt_id, v),
+                None => self.set_fixture_value(src, fixt_id, Dimmer, v),
+            },
+            Attribute::Pan(deg) => self.set_fixture_degrees(src, fixt_id, Pan, deg),
+            Attribute::Tilt(deg) => self.set_fixture_degrees(src, fixt_id, Tilt, deg),
+            Attribute::Point(target) => self.point_at(src, fixt_id, target),
+            Attribute::Shutter(state) => self.set_shutter(src, fixt_id, state),
+            Attribute::Capability { purpose, name, physical } => {
+                self.set_capability(src, fixt_id, purpose, &name, physical)
+            }
+            Attribute::Color(color) => {
+                let (fixt, mode) = self.layout(fixt_id)?;
+                let mixing = self.color_mixing(fixt);
+                let vals = mixing.mix(color, &mode.channel_purposes);
+                if vals.is_empty() {
+                    let slot = mixing
+                        .nearest_slot(mode.capabilities(ColorWheel), color)
+                        .ok_or_else(|| DmGuiError::dmx(format!("fixture {fixt_id} has no color channels")))?
+                        .value();
+                    return self.set_fixture_raw(src, fixt_id, ColorWheel, slot);
+                }
+                for (p, v) in vals {
+                    self.set_fixture_value(src, fixt_id, p, v)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes an attribute to all fixtures of a target. Fixtures lacking the attribute are skipped,
+    /// an error is only returned if the target doesn't resolve to any fixture.
+    pub fn set_attr_target(&mut self, src: Source, target: &Target, attr: Attribute) -> Result<(), DmGuiError> {
+        let fixts = self.resolve(target);
+        if fixts.is_empty() {
+            return Err(DmGuiError::dmx(format!("{target:?} does not refer to any fixture")));
+        }
+        for fixt_id in fixts {
+            if let Err(e) = self.set_attr(src, fixt_id, attr.clone()) {
+                log::trace!("{e}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the merged value of an attribute of a fixture, the one of the first cell if only the
+    /// cells provide it
+    pub fn attr(&self, fixt_id: usize, kind: AttributeKind) -> Option<Attribute> {
+        use ChannelPurpose::*;
+        if let Some(cell) = self.attr_cells(fixt_id, kind).first() {
+            return self.attr(*cell, kind);
+        }
+        match kind {
+            AttributeKind::Intensity => self
+                .virtual_intensity(fixt_id)
+                .or_else(|| self.fixture_value(fixt_id, Dimmer))
+                .map(Attribute::Intensity),
+            AttributeKind::Pan => self.fixture_degrees(fixt_id, Pan).map(Attribute::Pan),
+            AttributeKind::Tilt => self.fixture_degrees(fixt_id, Tilt).map(Attribute::Tilt),
+            // The distance to the point is lost in the pan/tilt values
+            AttributeKind::Point => None,
+            AttributeKind::Shutter => self.shutter(fixt_id).map(Attribute::Shutter),
+            AttributeKind::Capability(purpose) => {
+                let cap = self.capability(fixt_id, purpose)?;
+                Some(Attribute::Capability {
+                    purpose,
+                    name: cap.name.clone(),
+                    physical: cap.physical_at(self.fixture_raw(fixt_id, purpose)?),
+                })
+            }
+            AttributeKind::Color => {
+                let (fixt, _) = self.layout(fixt_id).ok()?;
+                let levels: Vec<(ChannelPurpose, f64)> = AttributeKind::Color
+                    .purposes()
+                    .into_iter()
+                    .filter_map(|p| Some((p, self.fixture_value(fixt_id, p)?)))
+                    .collect();
+                self.color_mixing(fixt)
+                    .unmix(&levels)
+                    .or_else(|| Some(self.capability(fixt_id, ColorWheel)?.color()?.into()))
+                    .map(Attribute::Color)
+            }
+        }
+    }
+
+    /// Finds the fixture and its channel patched to a channel of a universe. Channels of cells
+    /// are found on the cell.
+    pub fn purpose_at(&self, universe_id: usize, ch: usize) -> Option<FixturePurpose> {
+        self.fixts
+            .iter()
+            .filter(|(_, f)| f.universe_id == universe_id && ch >= f.start)
+            .filter(|(_, f)| {
+                // The channels of a fixture left to its cells are NoType
+                let purpose = self.fixture_mode(f).and_then(|m| m.channel_purposes.get(ch - f.start));
+                matches!(purpose, Some(p) if *p != ChannelPurpose::NoType || f.cells.is_empty())
+            })
+            .max_by_key(|(_, f)| f.cell_of.is_some())
+            .map(|(id, f)| FixturePurpose {
+                fixture_id: *id,
+                fixture_purpose_id: ch - f.start,
+            })
+    }
+
+    /// Fixture and attribute a channel of a universe is controlling
+    pub fn attribute_at(&self, universe_id: usize, ch: usize) -> Option<(usize, AttributeKind)> {
+        let fp = self.purpose_at(universe_id, ch)?;
+        let (_, mode) = self.layout(fp.fixture_id).ok()?;
+        let purpose = mode.channel_purposes[fp.fixture_purpose_id];
+        Some((fp.fixture_id, purpose.attribute()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fine_channels_crossing_the_universe_end_write_nothing() {
+        let mut d = DmxState::new(1);
+        // 16 bit dimmer at the last channel
+        let id = d.add_fixture(Fixture::new("Dimmer", 1, 0, 1, 0, UNIVERSE_SIZE - 1));
+        assert!(d.set_fixture_value(Source::Manual, id, ChannelPurpose::Dimmer, 1.).is_err());
+        assert_eq!(d.universes[&0].get(UNIVERSE_SIZE - 1), 0);
+
+        let id = d.add_fixture(Fixture::new("Dimmer", 2, 0, 1, 0, 0));
+        d.set_fixture_value(Source::Manual, id, ChannelPurpose::Dimmer, 0.5).unwrap();
+        assert_eq!(d.universes[&0].frame()[..2], [128, 0]);
+        assert_eq!(d.fixture_value(id, ChannelPurpose::Dimmer), Some(combine_value(&[128, 0])));
+    }
+
+    #[test]
+    fn removed_universes_come_back_with_their_channels() {
+        let mut d = DmxState::new(2);
+        d.universes.get_mut(&1).unwrap().set(3, 42);
+        d.park(1, 4, 200).unwrap();
+        let removed = d.remove_universe(1).unwrap();
+        assert!(!d.universes.contains_key(&1));
+        assert!(!d.modifiers.parked.contains_key(&1));
+
+        d.set_universe(1, Some(removed));
+        assert_eq!(d.universes[&1].get(3), 42);
+        assert_eq!(d.universes[&1].get(4), 200);
+        assert_eq!(d.modifiers.parked(1, 4), Some(200));
     }
 }
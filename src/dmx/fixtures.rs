@@ -1,6 +1,12 @@
+use super::{Curve, Placement};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Debug;
 
-#[derive(Debug, Clone)]
+pub const DEFAULT_PAN_RANGE: f64 = 540.;
+pub const DEFAULT_TILT_RANGE: f64 = 270.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ChannelPurpose {
     NoType,
 
@@ -14,17 +20,215 @@ pub enum ChannelPurpose {
     ColY,
     ColK,
 
+    ColA,
+    ColUv,
+    ColL,
+    ColorWheel,
+    Cto,
+    Ctc,
+
     Dimmer,
+    DimmerFine,
+    Shutter,
+    Strobe,
 
     Pan,
     PanFine,
+    PanUltra,
     Tilt,
     TiltFine,
+    TiltUltra,
+    PanTiltSpeed,
+
+    Zoom,
+    Focus,
+    Iris,
+    Frost,
+    Prism,
+    PrismRotation,
+    Gobo1,
+    Gobo1Rotation,
+    Gobo2,
+    Gobo2Rotation,
+
+    Speed,
+    Macro,
+    Control,
+}
+
+impl ChannelPurpose {
+    pub const ALL: &'static [ChannelPurpose] = &[
+        Self::NoType,
+        Self::ColR,
+        Self::ColG,
+        Self::ColB,
+        Self::ColW,
+        Self::ColC,
+        Self::ColM,
+        Self::ColY,
+        Self::ColK,
+        Self::ColA,
+        Self::ColUv,
+        Self::ColL,
+        Self::ColorWheel,
+        Self::Cto,
+        Self::Ctc,
+        Self::Dimmer,
+        Self::DimmerFine,
+        Self::Shutter,
+        Self::Strobe,
+        Self::Pan,
+        Self::PanFine,
+        Self::PanUltra,
+        Self::Tilt,
+        Self::TiltFine,
+        Self::TiltUltra,
+        Self::PanTiltSpeed,
+        Self::Zoom,
+        Self::Focus,
+        Self::Iris,
+        Self::Frost,
+        Self::Prism,
+        Self::PrismRotation,
+        Self::Gobo1,
+        Self::Gobo1Rotation,
+        Self::Gobo2,
+        Self::Gobo2Rotation,
+        Self::Speed,
+        Self::Macro,
+        Self::Control,
+    ];
+
+    /// Purpose by its name as shown in the UI, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|p| format!("{p:?}").eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Channels extending the resolution of this one, most significant first
+    pub fn fine_purposes(&self) -> &'static [ChannelPurpose] {
+        match self {
+            Self::Dimmer => &[Self::DimmerFine],
+            Self::Pan => &[Self::PanFine, Self::PanUltra],
+            Self::Tilt => &[Self::TiltFine, Self::TiltUltra],
+            _ => &[],
+        }
+    }
+
+    pub fn is_fine(&self) -> bool {
+        matches!(
+            self,
+            Self::DimmerFine | Self::PanFine | Self::PanUltra | Self::TiltFine | Self::TiltUltra
+        )
+    }
+
+    /// Value the channel rests at when nothing controls it: dark, centered and without effects.
+    /// Fixture modes can override this per channel.
+    pub fn home(&self) -> u8 {
+        match self {
+            Self::Pan | Self::Tilt | Self::Focus => 128,
+            Self::Shutter => 255,
+            _ => 0,
+        }
+    }
+
+    /// Value for locating a fixture: full open white with the beam open and pan/tilt at home.
+    /// Fixture modes can override this per channel.
+    pub fn highlight(&self) -> u8 {
+        match self {
+            Self::Dimmer | Self::DimmerFine => 255,
+            Self::ColR | Self::ColG | Self::ColB | Self::ColW => 255,
+            _ => self.home(),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// A patched instance of a fixture type. The channel layout is taken from mode `mode` of the
+/// library type `type_id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Fixture {
+    pub name: String,
+    pub number: usize,
+    pub type_id: usize,
+    pub mode: usize,
     pub universe_id: usize,
     pub start: usize,
-    pub channel_purposes: Vec<ChannelPurpose>,
+    /// Output curves overriding the ones of the fixture mode
+    #[serde(default)]
+    pub curves: HashMap<ChannelPurpose, Curve>,
+    /// Stage position and calibration of moving heads
+    #[serde(default)]
+    pub placement: Option<Placement>,
+    /// Parent fixture and index of the cell this fixture stands for, see [`super::Cell`]
+    #[serde(default)]
+    pub cell_of: Option<(usize, usize)>,
+    /// Fixtures standing for the cells of the mode, in cell order
+    #[serde(default)]
+    pub cells: Vec<usize>,
+}
+
+impl Fixture {
+    pub fn new<S: Into<String>>(name: S, number: usize, type_id: usize, mode: usize, universe_id: usize, start: usize) -> Self {
+        Self {
+            name: name.into(),
+            number,
+            type_id,
+            mode,
+            universe_id,
+            start,
+            curves: HashMap::new(),
+            placement: None,
+            cell_of: None,
+            cells: Vec::new(),
+        }
+    }
+}
+
+/// Splits a normalized value (0..=1) into `bytes` channel values, most significant byte first
+pub fn split_value(v: f64, bytes: usize) -> Vec<u8> {
+    let max = (1u64 << (8 * bytes)) - 1;
+    let raw = (v.clamp(0., 1.) * max as f64).round() as u64;
+    (0..bytes).rev().map(|b| (raw >> (8 * b)) as u8).collect()
+}
+
+/// Combines channel values (most significant byte first) into a normalized value (0..=1)
+pub fn combine_value(vals: &[u8]) -> f64 {
+    if vals.is_empty() {
+        return 0.;
+    }
+    let max = (1u64 << (8 * vals.len())) - 1;
+    let raw = vals.iter().fold(0u64, |acc, v| (acc << 8) | *v as u64);
+    raw as f64 / max as f64
+}
+
+/// Converts degrees around the center of `range` into a normalized value
+pub fn degrees_to_value(deg: f64, range: f64) -> f64 {
+    (deg / range + 0.5).clamp(0., 1.)
+}
+
+pub fn value_to_degrees(v: f64, range: f64) -> f64 {
+    (v - 0.5) * range
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_and_combine_round_trip() {
+        assert_eq!(split_value(1., 2), vec![255, 255]);
+        assert_eq!(split_value(0.5, 1), vec![128]);
+        assert_eq!(split_value(0.5, 2), vec![128, 0]);
+        assert_eq!(split_value(-1., 3), vec![0, 0, 0]);
+        for bytes in 1..=3 {
+            for v in [0., 0.25, 0.5, 0.999, 1.] {
+                let max = ((1u64 << (8 * bytes)) - 1) as f64;
+                assert!((combine_value(&split_value(v, bytes)) - v).abs() <= 0.5 / max);
+            }
+        }
+        assert_eq!(combine_value(&[]), 0.);
+        assert_eq!(combine_value(&[1, 0]), 256. / 65535.);
+    }
 }
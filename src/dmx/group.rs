@@ -0,0 +1,216 @@
+use super::DmxState;
+use crate::error::DmGuiError;
+use serde::{Deserialize, Serialize};
+
+/// Named list of fixture ids. The order of the members is kept, effects run through them in it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureGroup {
+    pub name: String,
+    pub members: Vec<usize>,
+}
+
+impl FixtureGroup {
+    pub fn new<S: Into<String>>(name: S, members: Vec<usize>) -> Self {
+        let mut n = Self {
+            name: name.into(),
+            members: Vec::with_capacity(members.len()),
+        };
+        members.into_iter().for_each(|m| n.add(m));
+        n
+    }
+
+    /// Appends a fixture, fixtures already in the group are not added twice
+    pub fn add(&mut self, fixt_id: usize) {
+        if !self.members.contains(&fixt_id) {
+            self.members.push(fixt_id);
+        }
+    }
+
+    pub fn remove(&mut self, fixt_id: usize) {
+        self.members.retain(|m| *m != fixt_id);
+    }
+
+    /// Moves the member at `from` to position `to`
+    pub fn move_member(&mut self, from: usize, to: usize) {
+        if from < self.members.len() {
+            let m = self.members.remove(from);
+            self.members.insert(to.min(self.members.len()), m);
+        }
+    }
+
+    pub fn reverse(&mut self) {
+        self.members.reverse();
+    }
+
+    /// Shuffles the members, the same seed always gives the same order
+    pub fn shuffle(&mut self, seed: u64) {
+        let mut rng = XorShift::new(seed);
+        for i in (1..self.members.len()).rev() {
+            let j = (rng.next() % (i as u64 + 1)) as usize;
+            self.members.swap(i, j);
+        }
+    }
+
+    /// Every `n`th member starting at position `offset`, e.g. `every_nth(2, 0)` gives the odd
+    /// members when counting from 1
+    pub fn every_nth(&self, n: usize, offset: usize) -> Vec<usize> {
+        self.members.iter().skip(offset).step_by(n.max(1)).copied().collect()
+    }
+
+    /// First, third, fifth... member
+    pub fn odd(&self) -> Vec<usize> {
+        self.every_nth(2, 0)
+    }
+
+    /// Second, fourth, sixth... member
+    pub fn even(&self) -> Vec<usize> {
+        self.every_nth(2, 1)
+    }
+}
+
+/// Small deterministic generator for reproducible shuffles
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // xorshift must not start at 0
+        Self((seed ^ 0x9E37_79B9_7F4A_7C15).max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+}
+
+/// What the graph or a sequence writes attributes to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Target {
+    Fixture(usize),
+    /// All members of the group with this name, in group order
+    Group(String),
+}
+
+impl DmxState {
+    /// Adds a group, its name gets a number appended if another group already uses it
+    pub fn add_group(&mut self, mut group: FixtureGroup) -> usize {
+        group.name = self.unique_group_name(&group.name);
+        let new_id = self.group_next_id;
+        self.group_next_id += 1;
+        self.fixt_groups.insert(new_id, group);
+        new_id
+    }
+
+    pub fn remove_group(&mut self, id: usize) -> Option<FixtureGroup> {
+        self.fixt_groups.remove(&id)
+    }
+
+    /// Renames a group, names are unique ignoring case so targets always resolve to one group
+    pub fn rename_group(&mut self, id: usize, name: &str) -> Result<(), DmGuiError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DmGuiError::dmx("group names must not be empty"));
+        }
+        if matches!(self.group_by_name(name), Some((other, _)) if other != id) {
+            return Err(DmGuiError::dmx(format!("there is a group named {name} already")));
+        }
+        let group = self
+            .fixt_groups
+            .get_mut(&id)
+            .ok_or_else(|| DmGuiError::dmx(format!("group {id} does not exist")))?;
+        group.name = name.to_string();
+        Ok(())
+    }
+
+    /// `name`, or `name` with the lowest number appended that no group uses yet
+    pub fn unique_group_name(&self, name: &str) -> String {
+        let name = name.trim();
+        (1..)
+            .map(|i| match i {
+                1 => name.to_string(),
+                i => format!("{name} {i}"),
+            })
+            .find(|n| self.group_by_name(n).is_none())
+            .unwrap()
+    }
+
+    /// Group by name, ignoring case. Names are unique, shows saved before that resolve to the
+    /// group with the lowest id.
+    pub fn group_by_name(&self, name: &str) -> Option<(usize, &FixtureGroup)> {
+        self.fixt_groups
+            .iter()
+            .filter(|(_, g)| g.name.eq_ignore_ascii_case(name.trim()))
+            .min_by_key(|(id, _)| **id)
+            .map(|(id, g)| (*id, g))
+    }
+
+    /// Members of a group that are still patched, in group order
+    pub fn group_fixtures(&self, id: usize) -> Vec<usize> {
+        self.fixt_groups
+            .get(&id)
+            .map(|g| g.members.iter().copied().filter(|m| self.fixts.contains_key(m)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Fixtures a target refers to, empty if it refers to nothing that exists
+    pub fn resolve(&self, target: &Target) -> Vec<usize> {
+        match target {
+            Target::Fixture(id) if self.fixts.contains_key(id) => vec![*id],
+            Target::Fixture(_) => Vec::new(),
+            Target::Group(name) => self
+                .group_by_name(name)
+                .map(|(id, _)| self.group_fixtures(id))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordering_operations() {
+        let mut g = FixtureGroup::new("g", vec![1, 2, 3, 2, 4, 5]);
+        assert_eq!(g.members, vec![1, 2, 3, 4, 5]);
+        assert_eq!(g.odd(), vec![1, 3, 5]);
+        assert_eq!(g.even(), vec![2, 4]);
+        assert_eq!(g.every_nth(3, 1), vec![2, 5]);
+        g.move_member(0, 10);
+        assert_eq!(g.members, vec![2, 3, 4, 5, 1]);
+        g.reverse();
+        assert_eq!(g.members, vec![1, 5, 4, 3, 2]);
+
+        let (mut a, mut b) = (g.clone(), g.clone());
+        a.shuffle(7);
+        b.shuffle(7);
+        assert_eq!(a, b);
+        a.members.sort_unstable();
+        assert_eq!(a.members, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let mut d = DmxState::new(1);
+        let a = d.add_group(FixtureGroup::new("Front", vec![]));
+        let b = d.add_group(FixtureGroup::new("front", vec![]));
+        assert_eq!(d.fixt_groups[&b].name, "front 2");
+        assert!(d.rename_group(b, "FRONT").is_err());
+        assert!(d.rename_group(b, " ").is_err());
+        d.rename_group(a, "FRONT").unwrap();
+        assert_eq!(d.group_by_name("front").map(|(id, _)| id), Some(a));
+    }
+
+    #[test]
+    fn duplicate_names_resolve_to_the_lowest_id() {
+        let mut d = DmxState::new(1);
+        for id in [5, 3, 9] {
+            d.fixt_groups.insert(id, FixtureGroup::new("Wash", vec![id]));
+        }
+        assert_eq!(d.group_by_name("wash").map(|(id, _)| id), Some(3));
+    }
+}
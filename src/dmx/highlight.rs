@@ -0,0 +1,147 @@
+use super::{DmxState, Source};
+use crate::error::DmGuiError;
+
+/// Fixtures shown in their highlight state for locating them. The highlight values are written to
+/// their own layer above all sources but park, so clearing it brings back what was programmed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub fixts: Vec<usize>,
+    /// Highest intensity of all other fixtures while something is highlighted, `None` leaves them
+    /// as is
+    pub dim_others: Option<f64>,
+    /// Group and member index stepped through with [`DmxState::highlight_step`]
+    pub step: Option<(usize, usize)>,
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Self {
+            fixts: Vec::new(),
+            dim_others: Some(0.),
+            step: None,
+        }
+    }
+}
+
+impl Highlight {
+    pub fn is_active(&self) -> bool {
+        !self.fixts.is_empty()
+    }
+
+    /// Intensity level of a fixture given its `programmed` level: full when highlighted, at most
+    /// the dim level for the other fixtures
+    pub fn level(&self, fixt_id: usize, programmed: f64) -> f64 {
+        match self.dim_others {
+            _ if !self.is_active() => programmed,
+            _ if self.fixts.contains(&fixt_id) => 1.,
+            Some(dim) => programmed.min(dim),
+            None => programmed,
+        }
+    }
+}
+
+impl DmxState {
+    /// Puts fixtures into their highlight state, replacing the previously highlighted ones.
+    /// Fixtures with cells are highlighted with all of their cells, cells with their parent.
+    /// Fixtures that can't be highlighted are skipped, it only fails if none of them could be.
+    pub fn highlight(&mut self, fixts: &[usize]) -> Result<(), DmGuiError> {
+        self.release_highlight();
+        self.highlight.fixts.clear();
+        let mut all: Vec<usize> = Vec::with_capacity(fixts.len());
+        for fixt_id in fixts {
+            let fixt = self.fixts.get(fixt_id);
+            let related = match fixt.and_then(|f| f.cell_of) {
+                Some((parent, _)) => vec![parent],
+                None => fixt.map(|f| f.cells.clone()).unwrap_or_default(),
+            };
+            for id in std::iter::once(*fixt_id).chain(related) {
+                if !all.contains(&id) {
+                    all.push(id);
+                }
+            }
+        }
+        // Parents first, their unused channels overlap with the cells
+        all.sort_by_key(|id| self.fixts.get(id).is_some_and(|f| f.cell_of.is_some()));
+        for fixt_id in &all {
+            let vals: Vec<u8> = match self.fixts.get(fixt_id).and_then(|f| self.fixture_mode(f)) {
+                Some(mode) => mode
+                    .channel_purposes
+                    .iter()
+                    .map(|p| mode.channel_defaults(*p).highlight)
+                    .collect(),
+                None => {
+                    log::debug!("not highlighting fixture {fixt_id}, it has no existing mode");
+                    continue;
+                }
+            };
+            match self.set_fixture_channels(Source::Highlight, *fixt_id, &vals) {
+                Ok(()) => self.highlight.fixts.push(*fixt_id),
+                Err(e) => log::debug!("not highlighting fixture {fixt_id}: {e}"),
+            }
+        }
+        if !fixts.is_empty() && self.highlight.fixts.is_empty() {
+            return Err(DmGuiError::dmx("none of the fixtures could be highlighted"));
+        }
+        Ok(())
+    }
+
+    /// Ends the highlight, all fixtures return to their programmed state
+    pub fn clear_highlight(&mut self) {
+        self.release_highlight();
+        self.highlight.fixts.clear();
+        self.highlight.step = None;
+    }
+
+    /// Highlights the next (`delta` 1) or previous (`delta` -1) member of a group and returns it.
+    /// Stepping starts at the first member if the group changed.
+    pub fn highlight_step(&mut self, group_id: usize, delta: isize) -> Result<usize, DmGuiError> {
+        let members = self.group_fixtures(group_id);
+        if members.is_empty() {
+            return Err(DmGuiError::dmx(format!("group {group_id} has no patched fixtures")));
+        }
+        let len = members.len() as isize;
+        let index = match self.highlight.step {
+            Some((g, i)) if g == group_id => (i as isize + delta).rem_euclid(len) as usize,
+            _ => 0,
+        };
+        self.highlight(&[members[index]])?;
+        self.highlight.step = Some((group_id, index));
+        Ok(members[index])
+    }
+
+    fn release_highlight(&mut self) {
+        for un in self.universes.values_mut() {
+            un.release_all(Source::Highlight);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dmx::Fixture;
+
+    #[test]
+    fn only_highlighted_fixtures_are_forced() {
+        let mut h = Highlight::default();
+        assert_eq!(h.level(0, 0.4), 0.4);
+        h.fixts = vec![0];
+        h.dim_others = Some(0.2);
+        assert_eq!(h.level(0, 0.4), 1.);
+        assert_eq!(h.level(1, 0.4), 0.2);
+        // Dimming never brightens a fixture that is lower already
+        assert_eq!(h.level(1, 0.1), 0.1);
+        h.dim_others = None;
+        assert_eq!(h.level(1, 0.4), 0.4);
+    }
+
+    #[test]
+    fn missing_fixtures_are_skipped() {
+        let mut d = DmxState::new(1);
+        let id = d.add_fixture(Fixture::new("Dimmer", 1, 0, 0, 0, 0));
+        d.highlight(&[id + 1, id]).unwrap();
+        assert_eq!(d.highlight.fixts, [id]);
+        assert!(d.highlight(&[id + 1]).is_err());
+        assert!(d.highlight.fixts.is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+use super::interpolate_pixels;
+use crate::color::Rgba;
+use crate::error::DmGuiError;
+use image::{AnimationDecoder, RgbaImage};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Longest side frames are scaled down to when loading, far more than pixel maps sample
+const MAX_SIDE: u32 = 256;
+
+/// One frame of an image source and how long it is shown in seconds. Stills and numbered
+/// sequences have no duration of their own and play at [`ImageSource::fps`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageFrame {
+    pub image: RgbaImage,
+    pub duration: Option<f64>,
+}
+
+impl ImageFrame {
+    /// Frame of `image`, scaled down to [`MAX_SIDE`] pixels if it is larger
+    pub fn new(image: RgbaImage, duration: Option<f64>) -> Self {
+        let (width, height) = image.dimensions();
+        let side = width.max(height);
+        let image = if side > MAX_SIDE {
+            let size = |s: u32| ((s as f64 * MAX_SIDE as f64 / side as f64).round() as u32).max(1);
+            image::imageops::resize(&image, size(width), size(height), image::imageops::FilterType::Triangle)
+        } else {
+            image
+        };
+        Self { image, duration }
+    }
+
+    /// Colour at normalized coordinates (0..=1) interpolated between the neighbouring pixels
+    pub fn sample(&self, u: f64, v: f64) -> Rgba {
+        let (width, height) = self.image.dimensions();
+        interpolate_pixels(width as usize, height as usize, u, v, |x, y| {
+            Rgba::from(self.image.get_pixel(x as u32, y as u32).0)
+        })
+    }
+}
+
+/// Still image, animated GIF or numbered sequence of image files (`wave_001.png`,
+/// `wave_002.png`...) played back as a colour source. The frames are decoded in the background by
+/// the [`ImageLoader`] once the source is rendered and are not stored with the show.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageSource {
+    /// The image, or any file of a numbered sequence
+    pub path: String,
+    /// Playback speed, 1 plays at the frame durations of the file
+    pub speed: f64,
+    pub looping: bool,
+    /// Frames per second of stills and numbered sequences, which have no durations of their own
+    pub fps: f64,
+    /// Part of the image used as `[left, top, right, bottom]`, normalized
+    pub crop: [f64; 4],
+    /// Size of the cropped image relative to the layout, 1 covers it exactly
+    pub scale: [f64; 2],
+    /// Position of the top left corner of the image in the layout, normalized
+    pub offset: [f64; 2],
+    #[serde(skip)]
+    pub frames: Arc<Vec<ImageFrame>>,
+    /// Time playback started at, the first frame rendered after loading starts it if unset
+    #[serde(skip)]
+    pub started: Option<f64>,
+}
+
+impl ImageSource {
+    pub fn new<S: Into<String>>(path: S) -> Self {
+        Self {
+            path: path.into(),
+            speed: 1.,
+            looping: true,
+            fps: 25.,
+            crop: [0., 0., 1., 1.],
+            scale: [1., 1.],
+            offset: [0., 0.],
+            frames: Arc::new(Vec::new()),
+            started: None,
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        !self.frames.is_empty()
+    }
+
+    /// Seconds a frame is shown at normal speed
+    pub fn frame_duration(&self, frame: &ImageFrame) -> f64 {
+        frame.duration.unwrap_or(1. / self.fps.max(0.01))
+    }
+
+    /// Length of one pass through all frames in seconds at normal speed
+    pub fn duration(&self) -> f64 {
+        self.frames.iter().map(|f| self.frame_duration(f)).sum()
+    }
+
+    /// Frame shown `elapsed` seconds after playback started
+    pub fn frame_at(&self, elapsed: f64) -> Option<&ImageFrame> {
+        let total = self.duration();
+        let mut time = elapsed * self.speed;
+        if total <= 0. {
+            return self.frames.first();
+        }
+        time = match self.looping {
+            true => time.rem_euclid(total),
+            false => time.clamp(0., total),
+        };
+        self.frames
+            .iter()
+            .find(|f| {
+                time -= self.frame_duration(f);
+                time < 0.
+            })
+            .or_else(|| self.frames.last())
+    }
+
+    /// Colour at normalized layout coordinates at time `t`, black outside of the image
+    pub fn sample(&self, u: f64, v: f64, t: f64) -> Rgba {
+        let frame = match self.frame_at(t - self.started.unwrap_or(t)) {
+            Some(f) => f,
+            None => return Rgba([0., 0., 0., 1.]),
+        };
+        let x = (u - self.offset[0]) / self.scale[0];
+        let y = (v - self.offset[1]) / self.scale[1];
+        if !(0. ..=1.).contains(&x) || !(0. ..=1.).contains(&y) {
+            return Rgba([0., 0., 0., 1.]);
+        }
+        let [left, top, right, bottom] = self.crop;
+        frame.sample(left + x * (right - left), top + y * (bottom - top))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ImageFile {
+    Decoding,
+    Decoded(Arc<Vec<ImageFrame>>),
+    Failed,
+}
+
+/// Decodes image files on background threads, so neither the GUI nor the output wait for the
+/// disk while holding the DMX state. Sources playing the same file share its frames.
+#[derive(Debug, Clone, Default)]
+pub struct ImageLoader {
+    files: Arc<Mutex<HashMap<String, ImageFile>>>,
+}
+
+impl ImageLoader {
+    /// Frames of the file at `path`, which starts decoding if it isn't yet. `None` while it is
+    /// decoded and if it failed, which is logged once.
+    pub fn frames(&self, path: &str) -> Option<Arc<Vec<ImageFrame>>> {
+        let mut files = self.files.lock();
+        match files.get(path) {
+            Some(ImageFile::Decoded(frames)) => return Some(frames.clone()),
+            Some(_) => return None,
+            None => files.insert(path.to_string(), ImageFile::Decoding),
+        };
+        let files = self.files.clone();
+        let path = path.to_string();
+        std::thread::spawn(move || {
+            let file = match load(Path::new(&path)) {
+                Ok(frames) => ImageFile::Decoded(Arc::new(frames)),
+                Err(e) => {
+                    log::warn!("Pixel map image: {e}");
+                    ImageFile::Failed
+                }
+            };
+            // Unless the file was dropped meanwhile
+            if let Some(f) = files.lock().get_mut(&path) {
+                *f = file;
+            }
+        });
+        None
+    }
+
+    /// Decodes the file at `path` again when its frames are requested next
+    pub fn reload(&self, path: &str) {
+        self.files.lock().remove(path);
+    }
+
+    /// Drops the frames of the files `keep` returns false for
+    pub fn retain(&self, keep: impl Fn(&str) -> bool) {
+        self.files.lock().retain(|path, _| keep(path));
+    }
+}
+
+/// Reads the frames of an image, GIF or numbered sequence
+fn load(path: &Path) -> Result<Vec<ImageFrame>, DmGuiError> {
+    let is_gif = path
+        .extension()
+        .is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case("gif"));
+    let frames = if is_gif {
+        load_gif(path)?
+    } else {
+        sequence_files(path)?
+            .iter()
+            .map(|f| Ok(ImageFrame::new(open(f)?.to_rgba8(), None)))
+            .collect::<Result<Vec<_>, DmGuiError>>()?
+    };
+    if frames.is_empty() {
+        return Err(DmGuiError::io(format!("{} contains no frames", path.display())));
+    }
+    Ok(frames)
+}
+
+fn open(path: &Path) -> Result<image::DynamicImage, DmGuiError> {
+    image::open(path).map_err(|e| DmGuiError::io(format!("could not load {}: {e}", path.display())))
+}
+
+fn load_gif(path: &Path) -> Result<Vec<ImageFrame>, DmGuiError> {
+    let err = |e: &dyn std::fmt::Display| DmGuiError::io(format!("could not load {}: {e}", path.display()));
+    let file = std::fs::File::open(path).map_err(|e| err(&e))?;
+    let decoder = image::codecs::gif::GifDecoder::new(std::io::BufReader::new(file)).map_err(|e| err(&e))?;
+    // Frames are scaled down one by one, the full size ones are never all in memory
+    decoder
+        .into_frames()
+        .map(|f| {
+            let f = f.map_err(|e| err(&e))?;
+            let (num, denom) = f.delay().numer_denom_ms();
+            // Browsers show GIFs without a delay at 10 fps as well
+            let duration = match num as f64 / denom.max(1) as f64 / 1000. {
+                d if d > 0. => d,
+                _ => 0.1,
+            };
+            Ok(ImageFrame::new(f.into_buffer(), Some(duration)))
+        })
+        .collect()
+}
+
+/// Files of the numbered sequence `path` belongs to in number order, just `path` if its name
+/// doesn't end in a number
+fn sequence_files(path: &Path) -> Result<Vec<PathBuf>, DmGuiError> {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    if prefix.len() == stem.len() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let ext = path.extension().map(|e| e.to_string_lossy().to_string());
+    let dir = match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let entries = std::fs::read_dir(&dir)
+        .map_err(|e| DmGuiError::io(format!("could not read {}: {e}", dir.display())))?;
+    let mut files: Vec<(u64, PathBuf)> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().map(|e| e.to_string_lossy().to_string()) == ext)
+        .filter_map(|p| {
+            let stem = p.file_stem()?.to_string_lossy().to_string();
+            let number = stem.strip_prefix(prefix)?.parse().ok()?;
+            Some((number, p))
+        })
+        .collect();
+    files.sort();
+    Ok(files.into_iter().map(|(_, p)| p).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn large_frames_are_scaled_down() {
+        let img = RgbaImage::from_fn(1024, 512, |x, _| match x < 512 {
+            true => image::Rgba([255, 0, 0, 255]),
+            false => image::Rgba([0, 0, 255, 255]),
+        });
+        let frame = ImageFrame::new(img, None);
+        assert_eq!(frame.image.dimensions(), (256, 128));
+        assert_eq!(frame.sample(0.1, 0.5), Rgba([1., 0., 0., 1.]));
+        assert_eq!(frame.sample(0.9, 0.5), Rgba([0., 0., 1., 1.]));
+
+        let small = RgbaImage::new(16, 8);
+        assert_eq!(ImageFrame::new(small, None).image.dimensions(), (16, 8));
+    }
+
+    #[test]
+    fn frames_without_duration_play_at_the_source_fps() {
+        let mut source = ImageSource::new("");
+        let frame = |v| ImageFrame::new(RgbaImage::from_pixel(1, 1, image::Rgba([v, v, v, 255])), None);
+        source.frames = Arc::new(vec![frame(0), frame(1)]);
+        source.fps = 2.;
+        assert_eq!(source.duration(), 1.);
+        assert_eq!(source.frame_at(0.75), Some(&source.frames[1]));
+        assert_eq!(source.frame_at(1.25), Some(&source.frames[0]));
+        source.looping = false;
+        assert_eq!(source.frame_at(1.25), Some(&source.frames[1]));
+    }
+
+    #[test]
+    fn files_are_decoded_in_the_background() {
+        let path = std::env::temp_dir().join(format!("pixel_map_{}.png", std::process::id()));
+        RgbaImage::from_pixel(4, 4, image::Rgba([0, 255, 0, 255])).save(&path).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let loader = ImageLoader::default();
+        let start = Instant::now();
+        let frames = loop {
+            match loader.frames(&path) {
+                Some(frames) => break frames,
+                None if start.elapsed() < Duration::from_secs(5) => std::thread::sleep(Duration::from_millis(10)),
+                None => panic!("{path} wasn't decoded"),
+            }
+        };
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].sample(0.5, 0.5), Rgba([0., 1., 0., 1.]));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(loader.frames("does_not_exist.png").is_none());
+        loader.retain(|p| p == path);
+        assert!(loader.files.lock().get("does_not_exist.png").is_none());
+    }
+}
@@ -0,0 +1,226 @@
+use super::{Attribution, ChannelPurpose, Contribution, DmxState, Fixture, FixtureMode, LayerStack, Source};
+use crate::error::DmGuiError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Channels scaled by the virtual intensity of fixtures without a dimmer. Subtractive colour
+/// channels are left out, scaling them would brighten the fixture.
+pub const VIRTUAL_INTENSITY_PURPOSES: &[ChannelPurpose] = &[
+    ChannelPurpose::ColR,
+    ChannelPurpose::ColG,
+    ChannelPurpose::ColB,
+    ChannelPurpose::ColW,
+    ChannelPurpose::ColA,
+    ChannelPurpose::ColUv,
+    ChannelPurpose::ColL,
+];
+
+/// Virtual intensity one source set for a fixture
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VirtualLevel {
+    pub source: Source,
+    pub writer: Option<u64>,
+    /// 0..=1
+    pub level: f64,
+    stamp: u64,
+    #[serde(skip, default = "Instant::now")]
+    changed: Instant,
+    /// Written since the last [`VirtualIntensities::release_stale`]
+    #[serde(skip)]
+    written: bool,
+}
+
+/// Virtual intensities of the fixtures per source. They are merged like channel values, with the
+/// priority and merge mode the source has in the universe of the fixture.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VirtualIntensities {
+    levels: HashMap<usize, Vec<VirtualLevel>>,
+    clock: u64,
+}
+
+impl VirtualIntensities {
+    /// Sets the level of `src`, like for channels a changed level counts as a new change
+    pub fn set(&mut self, src: Source, writer: Option<u64>, fixt_id: usize, level: f64) {
+        let level = level.clamp(0., 1.);
+        self.clock += 1;
+        let levels = self.levels.entry(fixt_id).or_default();
+        match levels.iter_mut().find(|l| l.source == src) {
+            Some(l) => {
+                l.writer = writer;
+                l.written = true;
+                if l.level != level {
+                    l.level = level;
+                    l.stamp = self.clock;
+                    l.changed = Instant::now();
+                }
+            }
+            None => levels.push(VirtualLevel {
+                source: src,
+                writer,
+                level,
+                stamp: self.clock,
+                changed: Instant::now(),
+                written: true,
+            }),
+        }
+    }
+
+    pub fn get(&self, src: Source, fixt_id: usize) -> Option<f64> {
+        self.levels.get(&fixt_id)?.iter().find(|l| l.source == src).map(|l| l.level)
+    }
+
+    pub fn release(&mut self, src: Source, fixt_id: usize) {
+        if let Some(levels) = self.levels.get_mut(&fixt_id) {
+            levels.retain(|l| l.source != src);
+        }
+        self.levels.retain(|_, l| !l.is_empty());
+    }
+
+    /// Releases the levels of `src` that weren't set since the last call, see
+    /// [`LayerStack::release_stale`]
+    pub fn release_stale(&mut self, src: Source) {
+        for levels in self.levels.values_mut() {
+            levels.retain(|l| l.source != src || l.written);
+            levels.iter_mut().filter(|l| l.source == src).for_each(|l| l.written = false);
+        }
+        self.levels.retain(|_, l| !l.is_empty());
+    }
+
+    /// Keeps the levels of the fixtures `keep` returns true for
+    pub fn retain_fixtures(&mut self, mut keep: impl FnMut(usize) -> bool) {
+        self.levels.retain(|id, _| keep(*id));
+    }
+
+    /// Level of a fixture winning the merge with the layer settings of its universe
+    pub fn winner(&self, fixt_id: usize, layers: &LayerStack) -> Option<&VirtualLevel> {
+        let contribution = |l: &VirtualLevel| {
+            let (priority, mode) = layers
+                .layer(l.source)
+                .map(|layer| (layer.priority, layer.mode))
+                .unwrap_or_else(|| (l.source.default_priority(), l.source.default_mode()));
+            Contribution {
+                priority,
+                mode,
+                value: l.level,
+                stamp: l.stamp,
+            }
+        };
+        let mut best: Option<&VirtualLevel> = None;
+        for l in self.levels.get(&fixt_id)? {
+            best = match best {
+                Some(b) if !contribution(l).beats(&contribution(b)) => Some(b),
+                _ => Some(l),
+            };
+        }
+        best
+    }
+}
+
+impl DmxState {
+    /// Whether the intensity of a fixture is applied to its colour channels at output time. This
+    /// is the case for modes without a dimmer channel if their type enables it.
+    pub fn has_virtual_intensity(&self, fixt: &Fixture) -> bool {
+        let enabled = self
+            .library
+            .types
+            .get(&fixt.type_id)
+            .map(|t| t.virtual_intensity)
+            .unwrap_or(false);
+        enabled
+            && self
+                .fixture_mode(fixt)
+                .map(|m| m.offset_of(ChannelPurpose::Dimmer).is_none())
+                .unwrap_or(false)
+    }
+
+    /// Merged virtual intensity of a fixture, full as long as no source sets it
+    pub fn virtual_intensity(&self, fixt_id: usize) -> Option<f64> {
+        self.virtual_level(fixt_id).map(|l| l.map_or(1., |l| l.level))
+    }
+
+    /// Who provides the virtual intensity of a fixture, see [`LayerStack::attribution`]
+    pub fn virtual_attribution(&self, fixt_id: usize) -> Option<Attribution> {
+        self.virtual_level(fixt_id)?.map(|l| Attribution {
+            source: l.source,
+            writer: l.writer,
+            changed: l.changed,
+        })
+    }
+
+    fn virtual_level(&self, fixt_id: usize) -> Option<Option<&VirtualLevel>> {
+        let fixt = self.fixts.get(&fixt_id)?;
+        if !self.has_virtual_intensity(fixt) {
+            return None;
+        }
+        let layers = self.universes.get(&fixt.universe_id).map(|un| un.layers());
+        Some(layers.and_then(|layers| self.virtual_intensity.winner(fixt_id, layers)))
+    }
+
+    pub fn set_virtual_intensity(&mut self, src: Source, fixt_id: usize, v: f64) -> Result<(), DmGuiError> {
+        let fixt = self
+            .fixts
+            .get(&fixt_id)
+            .ok_or_else(|| DmGuiError::dmx(format!("fixture {fixt_id} does not exist")))?;
+        if !self.has_virtual_intensity(fixt) {
+            return Err(DmGuiError::dmx(format!("fixture {fixt_id} has no channel for Dimmer")));
+        }
+        self.virtual_intensity.set(src, None, fixt_id, v);
+        Ok(())
+    }
+
+    /// Channels of a fixture carrying its intensity: the dimmer, or the colour channels if the
+    /// intensity is virtual
+    pub fn intensity_purposes(&self, fixt: &Fixture, mode: &FixtureMode) -> Vec<ChannelPurpose> {
+        let virt = self.has_virtual_intensity(fixt);
+        mode.channel_purposes
+            .iter()
+            .copied()
+            .filter(|p| match virt {
+                true => VIRTUAL_INTENSITY_PURPOSES.contains(p),
+                false => *p == ChannelPurpose::Dimmer,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_merge_per_source() {
+        let layers = LayerStack::new();
+        let mut v = VirtualIntensities::default();
+        assert!(v.winner(0, &layers).is_none());
+
+        v.set(Source::Graph, Some(7), 0, 0.8);
+        v.set(Source::Manual, None, 0, 0.3);
+        assert_eq!(v.winner(0, &layers).map(|l| l.level), Some(0.3));
+        // Repeating the same level is no new change
+        v.set(Source::Graph, Some(7), 0, 0.8);
+        assert_eq!(v.winner(0, &layers).map(|l| l.level), Some(0.3));
+        v.set(Source::Graph, Some(7), 0, 0.5);
+        assert_eq!(v.winner(0, &layers).map(|l| (l.source, l.writer)), Some((Source::Graph, Some(7))));
+
+        v.set(Source::Park, None, 0, 0.1);
+        v.set(Source::Manual, None, 0, 1.);
+        assert_eq!(v.winner(0, &layers).map(|l| l.level), Some(0.1));
+        v.release(Source::Park, 0);
+        assert_eq!(v.winner(0, &layers).map(|l| l.level), Some(1.));
+    }
+
+    #[test]
+    fn stale_levels_are_released() {
+        let mut v = VirtualIntensities::default();
+        v.set(Source::Graph, None, 0, 0.5);
+        v.set(Source::Graph, None, 1, 0.5);
+        v.set(Source::Manual, None, 1, 0.2);
+        v.release_stale(Source::Graph);
+        v.set(Source::Graph, None, 0, 0.5);
+        v.release_stale(Source::Graph);
+        assert_eq!(v.get(Source::Graph, 0), Some(0.5));
+        assert_eq!(v.get(Source::Graph, 1), None);
+        assert_eq!(v.get(Source::Manual, 1), Some(0.2));
+    }
+}
@@ -0,0 +1,347 @@
+use super::{cell_row, Capability, Cell, ChannelPurpose, ColorMixing, Curve, PhysicalUnit, DEFAULT_PAN_RANGE, DEFAULT_TILT_RANGE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Channel values of a fixture mode overriding the defaults of its purpose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelDefaults {
+    pub home: u8,
+    pub highlight: u8,
+}
+
+impl ChannelDefaults {
+    pub fn of(purpose: ChannelPurpose) -> Self {
+        Self {
+            home: purpose.home(),
+            highlight: purpose.highlight(),
+        }
+    }
+}
+
+/// One channel layout (footprint) a fixture type can be run in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixtureMode {
+    pub name: String,
+    pub channel_purposes: Vec<ChannelPurpose>,
+    pub pan_range: f64,
+    pub tilt_range: f64,
+    /// Named DMX ranges of channels that aren't linear, like gobo or strobe channels
+    #[serde(default)]
+    pub capabilities: HashMap<ChannelPurpose, Vec<Capability>>,
+    #[serde(default)]
+    pub defaults: HashMap<ChannelPurpose, ChannelDefaults>,
+    /// Output curves of channels, channels without one are linear
+    #[serde(default)]
+    pub curves: HashMap<ChannelPurpose, Curve>,
+    /// Cells of multi-cell fixtures, their channels lie behind or between the channels of the
+    /// mode, which are shared by all cells. Unused offsets in between are `NoType`.
+    #[serde(default)]
+    pub cells: Vec<Cell>,
+}
+
+impl FixtureMode {
+    pub fn new<S: Into<String>>(name: S, channel_purposes: Vec<ChannelPurpose>) -> Self {
+        Self {
+            name: name.into(),
+            channel_purposes,
+            pan_range: DEFAULT_PAN_RANGE,
+            tilt_range: DEFAULT_TILT_RANGE,
+            capabilities: HashMap::new(),
+            defaults: HashMap::new(),
+            curves: HashMap::new(),
+            cells: Vec::new(),
+        }
+    }
+
+    pub fn with_capabilities(mut self, purpose: ChannelPurpose, caps: Vec<Capability>) -> Self {
+        self.capabilities.insert(purpose, caps);
+        self
+    }
+
+    pub fn with_cells(mut self, cells: Vec<Cell>) -> Self {
+        self.cells = cells;
+        self
+    }
+
+    /// Number of channels including the ones of the cells
+    pub fn footprint(&self) -> usize {
+        self.cells
+            .iter()
+            .map(|c| c.end())
+            .fold(self.channel_purposes.len(), usize::max)
+    }
+
+    pub fn offset_of(&self, purpose: ChannelPurpose) -> Option<usize> {
+        self.channel_purposes.iter().position(|p| *p == purpose)
+    }
+
+    /// Offsets of `purpose` followed by its fine channels, as far as the mode has them.
+    /// An 8 bit channel yields one offset, a 16 bit one two and a 24 bit one three.
+    pub fn value_offsets(&self, purpose: ChannelPurpose) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        if let Some(coarse) = self.offset_of(purpose) {
+            offsets.push(coarse);
+            for fine in purpose.fine_purposes() {
+                match self.offset_of(*fine) {
+                    Some(o) => offsets.push(o),
+                    None => break,
+                }
+            }
+        }
+        offsets
+    }
+
+    pub fn with_defaults(mut self, purpose: ChannelPurpose, home: u8, highlight: u8) -> Self {
+        self.defaults.insert(purpose, ChannelDefaults { home, highlight });
+        self
+    }
+
+    pub fn channel_defaults(&self, purpose: ChannelPurpose) -> ChannelDefaults {
+        self.defaults
+            .get(&purpose)
+            .copied()
+            .unwrap_or_else(|| ChannelDefaults::of(purpose))
+    }
+
+    pub fn with_curve(mut self, purpose: ChannelPurpose, curve: Curve) -> Self {
+        self.curves.insert(purpose, curve);
+        self
+    }
+
+    pub fn capabilities(&self, purpose: ChannelPurpose) -> &[Capability] {
+        self.capabilities.get(&purpose).map(|c| c.as_slice()).unwrap_or_default()
+    }
+
+    /// Capability of `purpose` covering the (coarse) DMX value `v`
+    pub fn capability_at(&self, purpose: ChannelPurpose, v: u8) -> Option<&Capability> {
+        self.capabilities(purpose).iter().find(|c| c.contains(v))
+    }
+
+    /// Capability of `purpose` by name, ignoring case
+    pub fn capability(&self, purpose: ChannelPurpose, name: &str) -> Option<&Capability> {
+        self.capabilities(purpose).iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Full movement range in degrees for purposes that are angles
+    pub fn degree_range(&self, purpose: ChannelPurpose) -> Option<f64> {
+        match purpose {
+            ChannelPurpose::Pan => Some(self.pan_range),
+            ChannelPurpose::Tilt => Some(self.tilt_range),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixtureType {
+    pub manufacturer: String,
+    pub model: String,
+    pub modes: Vec<FixtureMode>,
+    /// Modes without a dimmer get an intensity scaling their colour channels at output time
+    #[serde(default = "enabled")]
+    pub virtual_intensity: bool,
+    #[serde(default)]
+    pub color_mixing: ColorMixing,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl FixtureType {
+    pub fn name(&self) -> String {
+        format!("{} {}", self.manufacturer, self.model)
+    }
+}
+
+/// Fixture types patched fixtures refer to by id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureLibrary {
+    pub next_id: usize,
+    pub types: HashMap<usize, FixtureType>,
+}
+
+impl FixtureLibrary {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            types: HashMap::new(),
+        }
+    }
+
+    /// Library containing a few generic types to get started
+    pub fn with_generic_types() -> Self {
+        use ChannelPurpose::*;
+        let mut n = Self::new();
+        let generic = |model: &str, modes: Vec<FixtureMode>| FixtureType {
+            manufacturer: "Generic".into(),
+            model: model.into(),
+            modes,
+            virtual_intensity: true,
+            color_mixing: ColorMixing::default(),
+        };
+        n.add_type(generic(
+            "Dimmer",
+            vec![
+                FixtureMode::new("8 bit", vec![Dimmer]),
+                FixtureMode::new("16 bit", vec![Dimmer, DimmerFine]),
+            ],
+        ));
+        n.add_type(generic(
+            "RGB",
+            vec![
+                FixtureMode::new("3ch", vec![ColR, ColG, ColB]),
+                FixtureMode::new("4ch", vec![Dimmer, ColR, ColG, ColB]),
+            ],
+        ));
+        n.add_type(generic(
+            "RGBW",
+            vec![
+                FixtureMode::new("4ch", vec![ColR, ColG, ColB, ColW]),
+                FixtureMode::new("5ch", vec![Dimmer, ColR, ColG, ColB, ColW]),
+            ],
+        ));
+        n.add_type(generic(
+            "Moving head",
+            vec![
+                FixtureMode::new("Basic", vec![Pan, Tilt, Dimmer, ColR, ColG, ColB]),
+                FixtureMode::new(
+                    "Extended",
+                    vec![Pan, PanFine, Tilt, TiltFine, Dimmer, DimmerFine, ColR, ColG, ColB, ColW],
+                ),
+            ],
+        ));
+        n.add_type(generic(
+            "Spot",
+            vec![FixtureMode::new(
+                "16ch",
+                vec![
+                    Pan, PanFine, Tilt, TiltFine, PanTiltSpeed, Shutter, Dimmer, ColorWheel, Gobo1,
+                    Gobo1Rotation, Prism, Focus, Zoom, Frost, Macro, Control,
+                ],
+            )
+            .with_capabilities(
+                Shutter,
+                vec![
+                    Capability::new("Closed", 0..=31),
+                    Capability::new("Strobe", 32..=95).with_physical(PhysicalUnit::Hertz, 1., 20.),
+                    Capability::new("Pulse", 96..=127).with_physical(PhysicalUnit::Hertz, 1., 10.),
+                    Capability::new("Random", 128..=159).with_physical(PhysicalUnit::Hertz, 1., 20.),
+                    Capability::new("Open", 160..=255),
+                ],
+            )
+            .with_capabilities(
+                ColorWheel,
+                vec![
+                    Capability::new("White", 0..=15).with_colors(vec![[1., 1., 1.]]),
+                    Capability::new("Red", 16..=31).with_colors(vec![[1., 0., 0.]]),
+                    Capability::new("Green", 32..=47).with_colors(vec![[0., 1., 0.]]),
+                    Capability::new("Blue", 48..=63).with_colors(vec![[0., 0., 1.]]),
+                    Capability::new("Yellow", 64..=79).with_colors(vec![[1., 1., 0.]]),
+                    Capability::new("Magenta", 80..=95).with_colors(vec![[1., 0., 1.]]),
+                    Capability::new("Magenta/White", 96..=111).with_colors(vec![[1., 0., 1.], [1., 1., 1.]]),
+                    Capability::new("Rotation", 128..=255)
+                        .with_physical(PhysicalUnit::DegreesPerSecond, 0., 360.),
+                ],
+            )
+            .with_capabilities(
+                Gobo1,
+                (0..8)
+                    .map(|i| match i {
+                        0 => Capability::new("Open", 0..=15),
+                        i => Capability::new(format!("Gobo {i}"), i * 16..=i * 16 + 15),
+                    })
+                    .collect(),
+            )
+            .with_capabilities(
+                Prism,
+                vec![Capability::new("Off", 0..=127), Capability::new("On", 128..=255)],
+            )
+            .with_capabilities(
+                Control,
+                vec![
+                    Capability::new("None", 0..=199),
+                    Capability::new("Reset", 200..=209),
+                    Capability::new("Lamp on", 210..=219),
+                    Capability::new("Lamp off", 220..=229),
+                ],
+            )],
+        ));
+        n.add_type(generic(
+            "LED bar",
+            vec![
+                FixtureMode::new("8 cells RGB", vec![]).with_cells(cell_row(8, 8, 0, &[ColR, ColG, ColB])),
+                FixtureMode::new("8 cells RGB + dimmer", vec![Dimmer])
+                    .with_cells(cell_row(8, 8, 1, &[ColR, ColG, ColB])),
+            ],
+        ));
+        n.add_type(generic(
+            "Matrix 5x5",
+            vec![FixtureMode::new("25 cells RGB", vec![]).with_cells(cell_row(25, 5, 0, &[ColR, ColG, ColB]))],
+        ));
+        n
+    }
+
+    pub fn add_type(&mut self, new: FixtureType) -> usize {
+        let new_id = self.next_id;
+        self.next_id += 1;
+        self.types.insert(new_id, new);
+        new_id
+    }
+
+    pub fn remove_type(&mut self, id: usize) -> Option<FixtureType> {
+        self.types.remove(&id)
+    }
+
+    pub fn mode(&self, type_id: usize, mode: usize) -> Option<&FixtureMode> {
+        self.types.get(&type_id)?.modes.get(mode)
+    }
+
+    /// Id of a type equal to `typ` including its modes, the type is added if there is none yet.
+    /// A type of the same name but with different modes is added as another type, so mode
+    /// indices of fixtures referring to `typ` stay valid.
+    pub fn find_or_add(&mut self, typ: &FixtureType) -> usize {
+        let found = self.types.iter().filter(|(_, t)| *t == typ).map(|(id, _)| *id).min();
+        match found {
+            Some(id) => id,
+            None => self.add_type(typ.clone()),
+        }
+    }
+}
+
+impl Default for FixtureLibrary {
+    fn default() -> Self {
+        Self::with_generic_types()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_or_add_compares_modes() {
+        let mut lib = FixtureLibrary::with_generic_types();
+        let count = lib.types.len();
+        let rgb = lib.types[&1].clone();
+        assert_eq!(lib.find_or_add(&rgb), 1);
+        assert_eq!(lib.types.len(), count);
+
+        let mut changed = rgb.clone();
+        changed.modes.truncate(1);
+        let id = lib.find_or_add(&changed);
+        assert_ne!(id, 1);
+        assert_eq!(lib.types[&id], changed);
+        assert_eq!(lib.find_or_add(&changed), id);
+    }
+
+    #[test]
+    fn value_offsets_follow_fine_channels() {
+        use ChannelPurpose::*;
+        let mode = FixtureMode::new("", vec![Dimmer, Pan, PanFine, PanUltra, Tilt, DimmerFine]);
+        assert_eq!(mode.value_offsets(Pan), vec![1, 2, 3]);
+        assert_eq!(mode.value_offsets(Dimmer), vec![0, 5]);
+        assert_eq!(mode.value_offsets(Tilt), vec![4]);
+        assert!(mode.value_offsets(ColR).is_empty());
+    }
+}
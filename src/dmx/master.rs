@@ -0,0 +1,141 @@
+use super::{DmxState, Target};
+use crate::error::DmGuiError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Scales the intensity of the fixtures of a group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Submaster {
+    pub name: String,
+    /// Name of the fixture group
+    pub group: String,
+    /// 0..=1
+    pub level: f64,
+    /// Held flash button, the submaster is at full while set
+    #[serde(skip)]
+    pub flash: bool,
+}
+
+impl Submaster {
+    pub fn new<S: Into<String>>(name: S, group: S) -> Self {
+        Self {
+            name: name.into(),
+            group: group.into(),
+            level: 1.,
+            flash: false,
+        }
+    }
+
+    pub fn effective_level(&self) -> f64 {
+        if self.flash {
+            1.
+        } else {
+            self.level.clamp(0., 1.)
+        }
+    }
+}
+
+/// Grand master, blackout and submasters, applied to intensity channels in the output stage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Masters {
+    /// 0..=1
+    pub grand: f64,
+    #[serde(skip)]
+    pub grand_flash: bool,
+    /// Intensity channels are sent as 0, all other channels are untouched
+    pub blackout: bool,
+    pub submasters: Vec<Submaster>,
+}
+
+impl Default for Masters {
+    fn default() -> Self {
+        Self {
+            grand: 1.,
+            grand_flash: false,
+            blackout: false,
+            submasters: Vec::new(),
+        }
+    }
+}
+
+impl Masters {
+    pub fn grand_level(&self) -> f64 {
+        if self.grand_flash {
+            1.
+        } else {
+            self.grand.clamp(0., 1.)
+        }
+    }
+
+    pub fn submaster_mut(&mut self, name: &str) -> Option<&mut Submaster> {
+        self.submasters
+            .iter_mut()
+            .find(|s| s.name.eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Operation on the masters, the common format for the GUI and remote inputs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MasterControl {
+    Grand(f64),
+    GrandFlash(bool),
+    Blackout(bool),
+    ToggleBlackout,
+    /// Level of a submaster by name
+    Submaster(String, f64),
+    SubmasterFlash(String, bool),
+}
+
+impl DmxState {
+    pub fn control(&mut self, ctl: MasterControl) -> Result<(), DmGuiError> {
+        let masters = &mut self.masters;
+        match ctl {
+            MasterControl::Grand(v) => masters.grand = v.clamp(0., 1.),
+            MasterControl::GrandFlash(on) => masters.grand_flash = on,
+            MasterControl::Blackout(on) => masters.blackout = on,
+            MasterControl::ToggleBlackout => masters.blackout = !masters.blackout,
+            MasterControl::Submaster(name, v) => {
+                masters
+                    .submaster_mut(&name)
+                    .ok_or_else(|| DmGuiError::dmx(format!("submaster {name} does not exist")))?
+                    .level = v.clamp(0., 1.);
+            }
+            MasterControl::SubmasterFlash(name, on) => {
+                masters
+                    .submaster_mut(&name)
+                    .ok_or_else(|| DmGuiError::dmx(format!("submaster {name} does not exist")))?
+                    .flash = on;
+            }
+        }
+        Ok(())
+    }
+
+    /// Factor the masters scale the intensity of each patched fixture with. Fixtures at full are
+    /// left out. Cells dimmed by their parent only get the masters the parent doesn't get.
+    pub fn master_levels(&self) -> HashMap<usize, f64> {
+        let grand = if self.masters.blackout { 0. } else { self.masters.grand_level() };
+        let parents: HashMap<usize, usize> = self
+            .fixts
+            .keys()
+            .filter_map(|id| Some((*id, self.dimming_parent(*id)?)))
+            .collect();
+        let mut levels: HashMap<usize, f64> = self
+            .fixts
+            .keys()
+            .map(|id| (*id, if parents.contains_key(id) { 1. } else { grand }))
+            .collect();
+        for sub in &self.masters.submasters {
+            let members = self.resolve(&Target::Group(sub.group.clone()));
+            for fixt_id in &members {
+                if parents.get(fixt_id).is_some_and(|p| members.contains(p)) {
+                    continue;
+                }
+                if let Some(l) = levels.get_mut(fixt_id) {
+                    *l *= sub.effective_level();
+                }
+            }
+        }
+        levels.retain(|_, l| *l < 1.);
+        levels
+    }
+}
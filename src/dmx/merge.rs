@@ -0,0 +1,359 @@
+use super::UNIVERSE_SIZE;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Everything that is able to contribute channel values to a universe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Source {
+    Input,
+    Graph,
+    Playback(usize),
+    Manual,
+    /// Colours sampled by the pixel maps, see [`crate::dmx::PixelMap`]
+    PixelMap,
+    /// Fixtures located with [`crate::dmx::DmxState::highlight`]
+    Highlight,
+    Park,
+}
+
+impl Source {
+    pub fn default_mode(&self) -> MergeMode {
+        match self {
+            Self::Input | Self::Graph | Self::PixelMap => MergeMode::Htp,
+            Self::Playback(_) | Self::Manual | Self::Highlight | Self::Park => MergeMode::Ltp,
+        }
+    }
+
+    pub fn default_priority(&self) -> u8 {
+        match self {
+            Self::Input => 50,
+            // Below everything operated directly, so fixtures can be taken out of a running map
+            Self::PixelMap => 90,
+            Self::Graph | Self::Playback(_) | Self::Manual => 100,
+            Self::Highlight => u8::MAX - 1,
+            Self::Park => u8::MAX,
+        }
+    }
+}
+
+/// Who provides the merged value of a channel. `writer` identifies the part of the source that
+/// wrote it (e.g. a graph node) and `changed` is the time the value was last changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attribution {
+    pub source: Source,
+    pub writer: Option<u64>,
+    pub changed: Instant,
+}
+
+/// How a layer competes with other layers of the same priority.
+/// Layers with a higher priority always win over lower ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergeMode {
+    /// Highest value wins
+    Htp,
+    /// Latest change wins
+    Ltp,
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub source: Source,
+    pub mode: MergeMode,
+    pub priority: u8,
+    /// Channels this layer is allowed to control at all
+    pub mask: Vec<bool>,
+    vals: Vec<u8>,
+    active: Vec<bool>,
+    stamps: Vec<u64>,
+    writers: Vec<Option<u64>>,
+    changed: Vec<Instant>,
+    /// Channels written since the last [`LayerStack::release_stale`]
+    written: Vec<bool>,
+}
+
+impl Layer {
+    pub fn new(source: Source) -> Self {
+        Self {
+            source,
+            mode: source.default_mode(),
+            priority: source.default_priority(),
+            mask: vec![true; UNIVERSE_SIZE],
+            vals: vec![0; UNIVERSE_SIZE],
+            active: vec![false; UNIVERSE_SIZE],
+            stamps: vec![0; UNIVERSE_SIZE],
+            writers: vec![None; UNIVERSE_SIZE],
+            changed: vec![Instant::now(); UNIVERSE_SIZE],
+            written: vec![false; UNIVERSE_SIZE],
+        }
+    }
+
+    pub fn get(&self, i: usize) -> Option<u8> {
+        self.contributes(i).then(|| self.vals[i])
+    }
+
+    /// Writes a value and returns if it changed anything, `stamp` is only taken over on change
+    /// so a source repeating the same value every frame doesn't win LTP merges.
+    fn write(&mut self, i: usize, n: u8, writer: Option<u64>, stamp: u64) -> bool {
+        self.writers[i] = writer;
+        self.written[i] = true;
+        if self.active[i] && self.vals[i] == n {
+            return false;
+        }
+        self.vals[i] = n;
+        self.active[i] = true;
+        self.stamps[i] = stamp;
+        self.changed[i] = Instant::now();
+        true
+    }
+
+    fn release(&mut self, i: usize) -> bool {
+        std::mem::replace(&mut self.active[i], false)
+    }
+
+    fn contributes(&self, i: usize) -> bool {
+        self.active[i] && self.mask[i]
+    }
+
+    fn contribution(&self, i: usize) -> Contribution<u8> {
+        Contribution {
+            priority: self.priority,
+            mode: self.mode,
+            value: self.vals[i],
+            stamp: self.stamps[i],
+        }
+    }
+}
+
+/// A value competing in a merge, `stamp` orders the changes of all contributions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contribution<T> {
+    pub priority: u8,
+    pub mode: MergeMode,
+    pub value: T,
+    pub stamp: u64,
+}
+
+impl<T: PartialOrd> Contribution<T> {
+    /// Whether this wins over `other`: the higher priority wins. Within the same priority the
+    /// higher value wins if both are HTP, otherwise the newer change wins, so the outcome doesn't
+    /// depend on the order the contributions are compared in.
+    pub fn beats(&self, other: &Self) -> bool {
+        if self.priority != other.priority {
+            return self.priority > other.priority;
+        }
+        match (self.mode, other.mode) {
+            (MergeMode::Htp, MergeMode::Htp) if self.value != other.value => self.value > other.value,
+            _ => self.stamp > other.stamp,
+        }
+    }
+}
+
+/// Ordered stack of layers composing into the output frame of one universe.
+#[derive(Debug, Clone)]
+pub struct LayerStack {
+    layers: Vec<Layer>,
+    clock: u64,
+}
+
+impl LayerStack {
+    pub fn new() -> Self {
+        let mut n = Self {
+            layers: Vec::new(),
+            clock: 0,
+        };
+        for src in [Source::Input, Source::Graph, Source::Manual, Source::Park] {
+            n.add_layer(Layer::new(src));
+        }
+        n
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn layer(&self, src: Source) -> Option<&Layer> {
+        self.layers.iter().find(|l| l.source == src)
+    }
+
+    /// Adds or replaces the layer of `new.source`, keeping the stack sorted by priority.
+    pub fn add_layer(&mut self, new: Layer) {
+        self.layers.retain(|l| l.source != new.source);
+        let pos = self.layers.partition_point(|l| l.priority <= new.priority);
+        self.layers.insert(pos, new);
+    }
+
+    pub fn remove_layer(&mut self, src: Source) -> Option<Layer> {
+        let pos = self.layers.iter().position(|l| l.source == src)?;
+        Some(self.layers.remove(pos))
+    }
+
+    /// Changes merge mode, priority or mask of a layer, the stack is re-sorted afterwards.
+    pub fn configure(&mut self, src: Source, f: impl FnOnce(&mut Layer)) {
+        if let Some(mut l) = self.remove_layer(src) {
+            f(&mut l);
+            self.add_layer(l);
+        }
+    }
+
+    fn layer_mut_or_insert(&mut self, src: Source) -> &mut Layer {
+        if self.layer(src).is_none() {
+            self.add_layer(Layer::new(src));
+        }
+        self.layers.iter_mut().find(|l| l.source == src).unwrap()
+    }
+
+    pub fn write(&mut self, src: Source, writer: Option<u64>, i: usize, n: u8) -> bool {
+        self.clock += 1;
+        let stamp = self.clock;
+        self.layer_mut_or_insert(src).write(i, n, writer, stamp)
+    }
+
+    pub fn release(&mut self, src: Source, i: usize) -> bool {
+        self.layers
+            .iter_mut()
+            .find(|l| l.source == src)
+            .map(|l| l.release(i))
+            .unwrap_or(false)
+    }
+
+    /// Releases the channels of `src` that weren't written since the last call and returns them.
+    /// Sources rewriting all of their channels every frame use this instead of releasing
+    /// everything first, which would make their unchanged values count as new changes.
+    pub fn release_stale(&mut self, src: Source) -> Vec<usize> {
+        let layer = match self.layers.iter_mut().find(|l| l.source == src) {
+            Some(l) => l,
+            None => return Vec::new(),
+        };
+        let stale: Vec<usize> = (0..UNIVERSE_SIZE).filter(|i| layer.active[*i] && !layer.written[*i]).collect();
+        for i in &stale {
+            layer.release(*i);
+        }
+        layer.written.iter_mut().for_each(|w| *w = false);
+        stale
+    }
+
+    /// Finds the layer providing channel `i`, see [`Contribution::beats`]
+    pub fn winner(&self, i: usize) -> Option<&Layer> {
+        let mut best: Option<&Layer> = None;
+        for l in self.layers.iter().filter(|l| l.contributes(i)) {
+            best = match best {
+                Some(b) if !l.contribution(i).beats(&b.contribution(i)) => Some(b),
+                _ => Some(l),
+            };
+        }
+        best
+    }
+
+    pub fn merge(&self, i: usize) -> u8 {
+        self.winner(i).map(|l| l.vals[i]).unwrap_or(0)
+    }
+
+    pub fn attribution(&self, i: usize) -> Option<Attribution> {
+        self.winner(i).map(|l| Attribution {
+            source: l.source,
+            writer: l.writers[i],
+            changed: l.changed[i],
+        })
+    }
+}
+
+impl Default for LayerStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(layers: &[(Source, MergeMode, u8)]) -> LayerStack {
+        let mut stack = LayerStack {
+            layers: Vec::new(),
+            clock: 0,
+        };
+        for (src, mode, priority) in layers {
+            let mut l = Layer::new(*src);
+            l.mode = *mode;
+            l.priority = *priority;
+            stack.add_layer(l);
+        }
+        stack
+    }
+
+    #[test]
+    fn htp_takes_the_highest_value() {
+        let mut s = stack(&[(Source::Input, MergeMode::Htp, 100), (Source::Graph, MergeMode::Htp, 100)]);
+        s.write(Source::Input, None, 0, 200);
+        s.write(Source::Graph, None, 0, 100);
+        assert_eq!(s.merge(0), 200);
+        s.write(Source::Graph, None, 0, 250);
+        assert_eq!(s.merge(0), 250);
+    }
+
+    #[test]
+    fn ltp_takes_the_latest_change() {
+        let mut s = stack(&[(Source::Manual, MergeMode::Ltp, 100), (Source::Playback(0), MergeMode::Ltp, 100)]);
+        s.write(Source::Manual, None, 0, 200);
+        s.write(Source::Playback(0), None, 0, 10);
+        assert_eq!(s.merge(0), 10);
+        // Repeating a value is no change and doesn't take over
+        s.write(Source::Manual, None, 0, 200);
+        assert_eq!(s.merge(0), 10);
+        s.write(Source::Manual, None, 0, 201);
+        assert_eq!(s.merge(0), 201);
+    }
+
+    #[test]
+    fn higher_priority_wins() {
+        let mut s = stack(&[(Source::Input, MergeMode::Htp, 50), (Source::Park, MergeMode::Ltp, 255)]);
+        s.write(Source::Park, None, 0, 0);
+        s.write(Source::Input, None, 0, 255);
+        assert_eq!(s.merge(0), 0);
+        assert_eq!(s.attribution(0).unwrap().source, Source::Park);
+        s.release(Source::Park, 0);
+        assert_eq!(s.merge(0), 255);
+    }
+
+    #[test]
+    fn mixed_modes_do_not_depend_on_layer_order() {
+        for order in [[0, 1], [1, 0]] {
+            let layers = [(Source::Graph, MergeMode::Htp, 100), (Source::Manual, MergeMode::Ltp, 100)];
+            let mut s = stack(&order.map(|i| layers[i]));
+            s.write(Source::Graph, None, 0, 255);
+            s.write(Source::Manual, None, 0, 10);
+            assert_eq!(s.merge(0), 10);
+            s.write(Source::Graph, None, 0, 5);
+            assert_eq!(s.merge(0), 5);
+        }
+    }
+
+    #[test]
+    fn stale_channels_are_released_without_touching_rewritten_ones() {
+        let mut s = stack(&[(Source::Graph, MergeMode::Htp, 100), (Source::Manual, MergeMode::Ltp, 100)]);
+        s.write(Source::Graph, None, 0, 100);
+        s.write(Source::Graph, None, 1, 100);
+        s.write(Source::Manual, None, 0, 50);
+        assert!(s.release_stale(Source::Graph).is_empty());
+
+        // The graph keeps writing channel 0 but not 1, the manual value stays the newest change
+        s.write(Source::Graph, None, 0, 100);
+        assert_eq!(s.release_stale(Source::Graph), vec![1]);
+        assert_eq!(s.merge(0), 50);
+        assert_eq!(s.merge(1), 0);
+    }
+
+    #[test]
+    fn mask_and_release_remove_contributions() {
+        let mut s = stack(&[(Source::Graph, MergeMode::Htp, 100)]);
+        s.write(Source::Graph, Some(7), 3, 99);
+        assert_eq!(s.attribution(3).unwrap().writer, Some(7));
+        s.configure(Source::Graph, |l| l.mask[3] = false);
+        assert_eq!(s.merge(3), 0);
+        assert!(s.attribution(3).is_none());
+        s.configure(Source::Graph, |l| l.mask[3] = true);
+        assert_eq!(s.merge(3), 99);
+        assert!(s.release(Source::Graph, 3));
+        assert_eq!(s.merge(3), 0);
+    }
+}
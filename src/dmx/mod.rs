@@ -1,10 +1,47 @@
+mod attribute;
+mod capability;
+mod cell;
 mod universe;
 mod channel;
+mod color_mix;
+mod curve;
 mod dmx;
 mod fixtures;
+mod group;
+mod highlight;
+mod image_source;
+mod intensity;
+mod library;
+mod master;
+mod merge;
+mod modifier;
+mod output;
+mod patch;
+mod pixel_map;
+mod placement;
+mod safety;
+mod shutter;
 
+pub use attribute::*;
+pub use capability::*;
+pub use cell::*;
 pub use universe::*;
 pub use channel::*;
+pub use color_mix::*;
+pub use curve::*;
 pub use dmx::*;
 pub use fixtures::*;
+pub use group::*;
+pub use highlight::*;
+pub use image_source::*;
+pub use intensity::*;
+pub use library::*;
+pub use master::*;
+pub use merge::*;
+pub use modifier::*;
+pub use patch::*;
+pub use pixel_map::*;
+pub use placement::*;
+pub use safety::*;
+pub use shutter::*;
 
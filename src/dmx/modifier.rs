@@ -0,0 +1,165 @@
+use super::{ChannelPurpose, DmxState, Source, UNIVERSE_SIZE};
+use crate::error::DmGuiError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Limits and inversion of a channel, applied to its output value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelModifier {
+    pub min: u8,
+    pub max: u8,
+    pub invert: bool,
+}
+
+impl Default for ChannelModifier {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: u8::MAX,
+            invert: false,
+        }
+    }
+}
+
+impl ChannelModifier {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply(&self, v: u8) -> u8 {
+        let v = if self.invert { u8::MAX - v } else { v };
+        v.clamp(self.min, self.max.max(self.min))
+    }
+
+    /// Like [`ChannelModifier::apply`] for normalized values of channels with fine channels.
+    /// The limits are given in steps of the coarse channel.
+    pub fn apply_normalized(&self, v: f64) -> f64 {
+        let v = if self.invert { 1. - v } else { v };
+        let (min, max) = (self.min as f64 / 255., self.max.max(self.min) as f64 / 255.);
+        v.clamp(min, max)
+    }
+}
+
+/// Output modifiers of the show, applied after the merge and the output curves.
+/// Parked channels keep their value regardless of any source or modifier.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Modifiers {
+    /// Per universe and channel
+    pub channels: HashMap<usize, HashMap<usize, ChannelModifier>>,
+    /// Per fixture and channel purpose, applied to the combined value of the fine channels
+    pub fixtures: HashMap<usize, HashMap<ChannelPurpose, ChannelModifier>>,
+    /// Parked values per universe and channel
+    pub parked: HashMap<usize, HashMap<usize, u8>>,
+}
+
+impl Modifiers {
+    pub fn channel(&self, universe_id: usize, ch: usize) -> Option<&ChannelModifier> {
+        self.channels.get(&universe_id)?.get(&ch)
+    }
+
+    pub fn fixture(&self, fixt_id: usize, purpose: ChannelPurpose) -> Option<&ChannelModifier> {
+        self.fixtures.get(&fixt_id)?.get(&purpose)
+    }
+
+    pub fn parked(&self, universe_id: usize, ch: usize) -> Option<u8> {
+        self.parked.get(&universe_id)?.get(&ch).copied()
+    }
+
+    /// Sets or (with `None` or an identity modifier) removes the modifier of a channel
+    pub fn set_channel(&mut self, universe_id: usize, ch: usize, modifier: Option<ChannelModifier>) {
+        match modifier.filter(|m| !m.is_identity()) {
+            Some(m) => {
+                self.channels.entry(universe_id).or_default().insert(ch, m);
+            }
+            None => {
+                if let Some(un) = self.channels.get_mut(&universe_id) {
+                    un.remove(&ch);
+                }
+            }
+        }
+    }
+
+    pub fn set_fixture(&mut self, fixt_id: usize, purpose: ChannelPurpose, modifier: Option<ChannelModifier>) {
+        match modifier.filter(|m| !m.is_identity()) {
+            Some(m) => {
+                self.fixtures.entry(fixt_id).or_default().insert(purpose, m);
+            }
+            None => {
+                if let Some(f) = self.fixtures.get_mut(&fixt_id) {
+                    f.remove(&purpose);
+                }
+            }
+        }
+    }
+}
+
+impl DmxState {
+    /// Freezes a channel at `v`. The value is also written to the park layer, so monitors show the
+    /// channel as parked.
+    pub fn park(&mut self, universe_id: usize, ch: usize, v: u8) -> Result<(), DmGuiError> {
+        if ch >= UNIVERSE_SIZE {
+            return Err(DmGuiError::dmx(format!("channel {ch} is outside of a universe")));
+        }
+        let un = self
+            .universes
+            .get_mut(&universe_id)
+            .ok_or_else(|| DmGuiError::dmx(format!("universe {universe_id} does not exist")))?;
+        un.set_source(Source::Park, ch, v);
+        self.modifiers.parked.entry(universe_id).or_default().insert(ch, v);
+        Ok(())
+    }
+
+    pub fn unpark(&mut self, universe_id: usize, ch: usize) {
+        if let Some(un) = self.modifiers.parked.get_mut(&universe_id) {
+            un.remove(&ch);
+        }
+        if let Some(un) = self.universes.get_mut(&universe_id) {
+            un.release(Source::Park, ch);
+        }
+    }
+
+    /// Parks all channels of a fixture at their current output values, a virtual intensity at its
+    /// current level
+    pub fn park_fixture(&mut self, fixt_id: usize) -> Result<(), DmGuiError> {
+        let (universe_id, chans) = self.fixture_channels(fixt_id)?;
+        let frame = self
+            .output_frame(universe_id)
+            .ok_or_else(|| DmGuiError::dmx(format!("universe {universe_id} does not exist")))?;
+        for ch in chans {
+            self.park(universe_id, ch, frame[ch])?;
+        }
+        if let Some(level) = self.virtual_intensity(fixt_id) {
+            self.set_virtual_intensity(Source::Park, fixt_id, level)?;
+        }
+        Ok(())
+    }
+
+    pub fn unpark_fixture(&mut self, fixt_id: usize) -> Result<(), DmGuiError> {
+        let (universe_id, chans) = self.fixture_channels(fixt_id)?;
+        chans.into_iter().for_each(|ch| self.unpark(universe_id, ch));
+        self.virtual_intensity.release(Source::Park, fixt_id);
+        Ok(())
+    }
+
+    /// Writes all parked values to the park layers again, e.g. after loading a show
+    pub fn restore_parked(&mut self) {
+        for (universe_id, chans) in &self.modifiers.parked {
+            if let Some(un) = self.universes.get_mut(universe_id) {
+                un.release_all(Source::Park);
+                for (ch, v) in chans {
+                    un.set_source(Source::Park, *ch, *v);
+                }
+            }
+        }
+    }
+
+    /// Universe and channels a fixture occupies
+    fn fixture_channels(&self, fixt_id: usize) -> Result<(usize, Vec<usize>), DmGuiError> {
+        let fixt = self
+            .fixts
+            .get(&fixt_id)
+            .ok_or_else(|| DmGuiError::dmx(format!("fixture {fixt_id} does not exist")))?;
+        let end = (fixt.start + self.footprint(fixt)).min(UNIVERSE_SIZE);
+        Ok((fixt.universe_id, (fixt.start..end).collect()))
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use super::{combine_value, split_value, ChannelPurpose, Curve, DmxState, Fixture, UNIVERSE_SIZE};
+
+impl DmxState {
+    /// Curve of a channel of a fixture, the fixture's own curve takes precedence over the mode's
+    pub fn curve<'a>(&'a self, fixt: &'a Fixture, purpose: ChannelPurpose) -> Option<&'a Curve> {
+        fixt.curves
+            .get(&purpose)
+            .or_else(|| self.fixture_mode(fixt)?.curves.get(&purpose))
+    }
+
+    /// Output frames of all universes as `(universe id, frame)`, see [`DmxState::output_frame`]
+    pub fn output_frames(&self) -> Vec<(usize, Vec<u8>)> {
+        let levels = self.master_levels();
+        self.universe_ids()
+            .into_iter()
+            .filter_map(|id| Some((id, self.frame_with_levels(id, &levels)?)))
+            .collect()
+    }
+
+    /// Frame sent out for a universe: the merged channel values scaled by the masters and the
+    /// virtual intensity (full for highlighted fixtures, at most the dim level for the others) and
+    /// passed through the output curves and the fixture modifiers of the patched fixtures, then the
+    /// channel modifiers. Parked channels are sent as parked.
+    pub fn output_frame(&self, universe_id: usize) -> Option<Vec<u8>> {
+        self.frame_with_levels(universe_id, &self.master_levels())
+    }
+
+    fn frame_with_levels(&self, universe_id: usize, levels: &HashMap<usize, f64>) -> Option<Vec<u8>> {
+        let un = self.universes.get(&universe_id)?;
+        let mut frame = un.frame().to_vec();
+
+        for (fixt_id, fixt) in self.fixts.iter().filter(|(_, f)| f.universe_id == universe_id) {
+            let mode = match self.fixture_mode(fixt) {
+                Some(m) => m,
+                None => continue,
+            };
+            let intensity = self.intensity_purposes(fixt, mode);
+            let programmed =
+                levels.get(fixt_id).copied().unwrap_or(1.) * self.virtual_intensity(*fixt_id).unwrap_or(1.);
+            let level = self.highlight.level(*fixt_id, programmed);
+            for purpose in mode.channel_purposes.iter().filter(|p| !p.is_fine()) {
+                let level = Some(level).filter(|l| *l < 1. && intensity.contains(purpose));
+                let curve = self.curve(fixt, *purpose).filter(|c| !c.is_linear());
+                let modifier = self.modifiers.fixture(*fixt_id, *purpose).filter(|m| !m.is_identity());
+                if level.is_none() && curve.is_none() && modifier.is_none() {
+                    continue;
+                }
+                let chans: Vec<usize> = mode.value_offsets(*purpose).into_iter().map(|o| fixt.start + o).collect();
+                if chans.iter().any(|c| *c >= UNIVERSE_SIZE) {
+                    continue;
+                }
+                let vals: Vec<u8> = chans.iter().map(|c| un.get(*c)).collect();
+                let mut v = combine_value(&vals);
+                if let Some(level) = level {
+                    v *= level;
+                }
+                if let Some(curve) = curve {
+                    v = curve.apply(v);
+                }
+                if let Some(modifier) = modifier {
+                    v = modifier.apply_normalized(v);
+                }
+                for (c, v) in chans.into_iter().zip(split_value(v, vals.len())) {
+                    frame[c] = v;
+                }
+            }
+        }
+
+        if let Some(chans) = self.modifiers.channels.get(&universe_id) {
+            for (ch, modifier) in chans.iter().filter(|(ch, _)| **ch < UNIVERSE_SIZE) {
+                frame[*ch] = modifier.apply(frame[*ch]);
+            }
+        }
+        if let Some(chans) = self.modifiers.parked.get(&universe_id) {
+            for (ch, v) in chans.iter().filter(|(ch, _)| **ch < UNIVERSE_SIZE) {
+                frame[*ch] = *v;
+            }
+        }
+        Some(frame)
+    }
+}
@@ -0,0 +1,197 @@
+use super::{DmxState, Fixture, UNIVERSE_SIZE};
+use crate::error::DmGuiError;
+use std::fmt;
+
+/// A problem with the patch, found by [`DmxState::validate_patch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchConflict {
+    /// Two fixtures share channels `from..to` of a universe
+    Overlap {
+        fixt_id: usize,
+        other: usize,
+        universe_id: usize,
+        from: usize,
+        to: usize,
+    },
+    /// The footprint of the fixture ends behind the last channel of its universe
+    OutOfUniverse { fixt_id: usize, end: usize },
+    UnknownUniverse { fixt_id: usize, universe_id: usize },
+    UnknownMode { fixt_id: usize, type_id: usize, mode: usize },
+}
+
+impl PatchConflict {
+    /// Fixtures involved in the conflict
+    pub fn fixtures(&self) -> Vec<usize> {
+        match self {
+            Self::Overlap { fixt_id, other, .. } => vec![*fixt_id, *other],
+            Self::OutOfUniverse { fixt_id, .. }
+            | Self::UnknownUniverse { fixt_id, .. }
+            | Self::UnknownMode { fixt_id, .. } => vec![*fixt_id],
+        }
+    }
+}
+
+impl fmt::Display for PatchConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Overlap { fixt_id, other, universe_id, from, to } => write!(
+                f,
+                "fixtures {fixt_id} and {other} overlap on channels {}-{to} of universe {universe_id}",
+                from + 1
+            ),
+            Self::OutOfUniverse { fixt_id, end } => write!(
+                f,
+                "fixture {fixt_id} ends at channel {end}, behind the end of its universe ({UNIVERSE_SIZE})"
+            ),
+            Self::UnknownUniverse { fixt_id, universe_id } => {
+                write!(f, "fixture {fixt_id} is patched to universe {universe_id}, which does not exist")
+            }
+            Self::UnknownMode { fixt_id, type_id, mode } => write!(
+                f,
+                "fixture {fixt_id} uses mode {mode} of fixture type {type_id}, which does not exist"
+            ),
+        }
+    }
+}
+
+impl DmxState {
+    /// Checks the whole patch and returns all conflicts found. Cells are checked as part of their
+    /// fixture.
+    pub fn validate_patch(&self) -> Vec<PatchConflict> {
+        let mut ids: Vec<usize> = self
+            .fixts
+            .iter()
+            .filter(|(_, f)| f.cell_of.is_none())
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable_by_key(|id| (self.fixts[id].universe_id, self.fixts[id].start, *id));
+
+        let mut conflicts = Vec::new();
+        for (i, id) in ids.iter().enumerate() {
+            let fixt = &self.fixts[id];
+            conflicts.extend(self.check_fixture(*id, fixt));
+
+            let end = fixt.start + self.footprint(fixt);
+            for other in &ids[i + 1..] {
+                let o = &self.fixts[other];
+                if o.universe_id != fixt.universe_id || o.start >= end {
+                    break;
+                }
+                let o_end = o.start + self.footprint(o);
+                if o_end > o.start {
+                    conflicts.push(PatchConflict::Overlap {
+                        fixt_id: *id,
+                        other: *other,
+                        universe_id: fixt.universe_id,
+                        from: o.start,
+                        to: end.min(o_end),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Conflicts of a single fixture not involving other fixtures
+    pub fn check_fixture(&self, fixt_id: usize, fixt: &Fixture) -> Vec<PatchConflict> {
+        let mut conflicts = Vec::new();
+        if self.fixture_mode(fixt).is_none() {
+            conflicts.push(PatchConflict::UnknownMode {
+                fixt_id,
+                type_id: fixt.type_id,
+                mode: fixt.mode,
+            });
+        }
+        if !self.universes.contains_key(&fixt.universe_id) {
+            conflicts.push(PatchConflict::UnknownUniverse {
+                fixt_id,
+                universe_id: fixt.universe_id,
+            });
+        }
+        let end = fixt.start + self.footprint(fixt);
+        if end > UNIVERSE_SIZE {
+            conflicts.push(PatchConflict::OutOfUniverse { fixt_id, end });
+        }
+        conflicts
+    }
+
+    /// First address at or behind `from` in `universe_id` with `footprint` unused channels.
+    /// Fixtures in `ignore` don't count as using their channels.
+    pub fn free_address_from(
+        &self,
+        universe_id: usize,
+        from: usize,
+        footprint: usize,
+        ignore: &[usize],
+    ) -> Option<usize> {
+        let used: Vec<(usize, usize)> = self
+            .fixts
+            .iter()
+            .filter(|(id, f)| f.universe_id == universe_id && f.cell_of.is_none() && !ignore.contains(id))
+            .map(|(_, f)| (f.start, f.start + self.footprint(f)))
+            .collect();
+        Self::first_fit(&used, from, footprint)
+    }
+
+    fn first_fit(used: &[(usize, usize)], from: usize, footprint: usize) -> Option<usize> {
+        let mut used = used.to_vec();
+        used.sort_unstable();
+
+        let mut addr = from;
+        for (start, end) in used {
+            if addr + footprint <= start {
+                break;
+            }
+            addr = addr.max(end);
+        }
+        (addr + footprint <= UNIVERSE_SIZE).then_some(addr)
+    }
+
+    /// Addresses `(universe, start)` for fixtures with `footprints` placed one after another from
+    /// `start` in `universe_id`, skipping used channels. Continues in the following universes when
+    /// one is full. Fixtures in `ignore` don't count as using their channels, so they can be
+    /// re-addressed.
+    pub fn sequential_addresses(
+        &self,
+        universe_id: usize,
+        start: usize,
+        footprints: &[usize],
+        ignore: &[usize],
+    ) -> Result<Vec<(usize, usize)>, DmGuiError> {
+        let mut universes: Vec<usize> = self.universes.keys().copied().filter(|u| *u >= universe_id).collect();
+        universes.sort_unstable();
+        let mut universes = universes.into_iter();
+
+        let mut un = universes
+            .next()
+            .filter(|u| *u == universe_id)
+            .ok_or_else(|| DmGuiError::dmx(format!("universe {universe_id} does not exist")))?;
+        let used_in = |un: usize| -> Vec<(usize, usize)> {
+            self.fixts
+                .iter()
+                .filter(|(id, f)| f.universe_id == un && f.cell_of.is_none() && !ignore.contains(id))
+                .map(|(_, f)| (f.start, f.start + self.footprint(f)))
+                .collect()
+        };
+        let mut used = used_in(un);
+        let mut cursor = start;
+
+        let mut addrs = Vec::with_capacity(footprints.len());
+        for footprint in footprints {
+            loop {
+                if let Some(addr) = Self::first_fit(&used, cursor, *footprint) {
+                    used.push((addr, addr + footprint));
+                    cursor = addr + footprint;
+                    addrs.push((un, addr));
+                    break;
+                }
+                un = universes.next().ok_or_else(|| {
+                    DmGuiError::dmx(format!("not enough free channels to patch {} fixtures", footprints.len()))
+                })?;
+                used = used_in(un);
+                cursor = 0;
+            }
+        }
+        Ok(addrs)
+    }
+}
@@ -0,0 +1,419 @@
+use super::{Attribute, DmxState, ImageSource, Source};
+use crate::error::DmGuiError;
+use crate::color::Rgba;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+/// Colours sampled by pixel maps, row by row from the top left
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgba>,
+}
+
+impl ColorBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Rgba([0., 0., 0., 1.]); width * height],
+        }
+    }
+
+    /// Buffer with the colour of each pixel given by `f` at the normalized pixel center
+    pub fn from_fn(width: usize, height: usize, f: impl Fn(f64, f64) -> Rgba) -> Self {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(f((x as f64 + 0.5) / width as f64, (y as f64 + 0.5) / height as f64));
+            }
+        }
+        Self { width, height, pixels }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Rgba> {
+        (x < self.width).then(|| self.pixels.get(y * self.width + x).copied()).flatten()
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Rgba) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    /// Colour at normalized coordinates (0..=1), see [`interpolate_pixels`]
+    pub fn sample(&self, u: f64, v: f64) -> Rgba {
+        if self.pixels.len() < self.width * self.height {
+            return Rgba([0., 0., 0., 1.]);
+        }
+        interpolate_pixels(self.width, self.height, u, v, |x, y| self.pixels[y * self.width + x])
+    }
+}
+
+/// Colour at normalized coordinates (0..=1) of an image of `width` × `height` pixels,
+/// interpolated between the neighbouring pixels given by `px(x, y)`. Black for an empty image.
+pub fn interpolate_pixels(width: usize, height: usize, u: f64, v: f64, px: impl Fn(usize, usize) -> Rgba) -> Rgba {
+    if width == 0 || height == 0 {
+        return Rgba([0., 0., 0., 1.]);
+    }
+    let x = (u.clamp(0., 1.) * width as f64 - 0.5).clamp(0., (width - 1) as f64);
+    let y = (v.clamp(0., 1.) * height as f64 - 0.5).clamp(0., (height - 1) as f64);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let top = mix(px(x0, y0), px(x1, y0), fx);
+    let bottom = mix(px(x0, y1), px(x1, y1), fx);
+    mix(top, bottom, fy)
+}
+
+/// Patterns computed for every pixel. Directions are angles in degrees, 0 runs from left to
+/// right and 90 from top to bottom. Speeds are in cycles per second.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Generator {
+    Solid(Rgba),
+    Gradient { from: Rgba, to: Rgba, angle: f64 },
+    /// Hue cycle repeated `scale` times across the layout
+    Rainbow { angle: f64, scale: f64, speed: f64 },
+    /// Soft bars of `color` on `background`, `scale` of them across the layout
+    Wave { color: Rgba, background: Rgba, angle: f64, scale: f64, speed: f64 },
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::Rainbow {
+            angle: 0.,
+            scale: 1.,
+            speed: 0.2,
+        }
+    }
+}
+
+impl Generator {
+    pub const NAMES: &'static [&'static str] = &["Solid", "Gradient", "Rainbow", "Wave"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Solid(_) => "Solid",
+            Self::Gradient { .. } => "Gradient",
+            Self::Rainbow { .. } => "Rainbow",
+            Self::Wave { .. } => "Wave",
+        }
+    }
+
+    /// Generator by name (ignoring case) with default settings
+    pub fn from_name(name: &str) -> Option<Self> {
+        let white = Rgba([1., 1., 1., 1.]);
+        let black = Rgba([0., 0., 0., 1.]);
+        match name.trim().to_ascii_lowercase().as_str() {
+            "solid" => Some(Self::Solid(white)),
+            "gradient" => Some(Self::Gradient {
+                from: black,
+                to: white,
+                angle: 0.,
+            }),
+            "rainbow" => Some(Self::default()),
+            "wave" => Some(Self::Wave {
+                color: white,
+                background: black,
+                angle: 0.,
+                scale: 1.,
+                speed: 0.5,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Colour at normalized layout coordinates (0..=1) `t` seconds after start
+    pub fn color_at(&self, u: f64, v: f64, t: f64) -> Rgba {
+        match self {
+            Self::Solid(c) => *c,
+            Self::Gradient { from, to, angle } => mix(*from, *to, along(u, v, *angle).clamp(0., 1.)),
+            Self::Rainbow { angle, scale, speed } => hue((along(u, v, *angle) * scale - t * speed).rem_euclid(1.)),
+            Self::Wave {
+                color,
+                background,
+                angle,
+                scale,
+                speed,
+            } => {
+                let phase = (along(u, v, *angle) * scale - t * speed) * TAU;
+                mix(*background, *color, 0.5 + 0.5 * phase.cos())
+            }
+        }
+    }
+}
+
+/// What a pixel map samples its colours from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PixelSource {
+    Generator(Generator),
+    /// Buffer provided from outside, e.g. by the graph
+    Buffer(ColorBuffer),
+    /// Still image or frame sequence loaded from disk
+    Image(ImageSource),
+}
+
+impl Default for PixelSource {
+    fn default() -> Self {
+        Self::Generator(Generator::default())
+    }
+}
+
+impl PixelSource {
+    pub fn sample(&self, u: f64, v: f64, t: f64) -> Rgba {
+        match self {
+            Self::Generator(g) => g.color_at(u, v, t),
+            Self::Buffer(b) => b.sample(u, v),
+            Self::Image(i) => i.sample(u, v, t),
+        }
+    }
+}
+
+/// A fixture placed in a pixel map. Its cells are placed around `position` by their cell
+/// position, fixtures without cells are a single pixel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MappedFixture {
+    pub fixt_id: usize,
+    pub position: [f64; 2],
+    /// Distance of neighbouring cells in layout units
+    pub cell_size: f64,
+    /// Rotation of the cells around `position` in degrees, clockwise
+    pub rotation: f64,
+}
+
+impl MappedFixture {
+    pub fn new(fixt_id: usize, position: [f64; 2]) -> Self {
+        Self {
+            fixt_id,
+            position,
+            cell_size: 1.,
+            rotation: 0.,
+        }
+    }
+}
+
+/// 2D layout of fixtures a colour source is sampled onto. Positions range from 0 to `size`, with
+/// the layout covering the whole source.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PixelMap {
+    pub name: String,
+    pub enabled: bool,
+    pub size: [f64; 2],
+    pub fixts: Vec<MappedFixture>,
+    pub source: PixelSource,
+}
+
+impl PixelMap {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            enabled: true,
+            size: [16., 16.],
+            fixts: Vec::new(),
+            source: PixelSource::default(),
+        }
+    }
+}
+
+impl DmxState {
+    /// Pixel map by name, ignoring case
+    pub fn pixel_map_mut(&mut self, name: &str) -> Option<&mut PixelMap> {
+        self.pixel_maps
+            .iter_mut()
+            .find(|m| m.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Replaces the source of a pixel map. An image source keeps the frames and playback of the
+    /// map if it plays the same file, other files are loaded when the map is rendered next.
+    pub fn set_pixel_source(&mut self, name: &str, mut source: PixelSource) -> Result<(), DmGuiError> {
+        let map = self
+            .pixel_map_mut(name)
+            .ok_or_else(|| DmGuiError::dmx(format!("There is no pixel map named {name}")))?;
+        if let (PixelSource::Image(new), PixelSource::Image(old)) = (&mut source, &map.source) {
+            if old.path == new.path {
+                new.frames = old.frames.clone();
+                new.started = old.started;
+            }
+        }
+        map.source = source;
+        Ok(())
+    }
+
+    /// Layout positions of the pixels of a mapped fixture as `(fixture, position)`: one per cell,
+    /// or the fixture itself if it has no cells
+    pub fn pixel_positions(&self, mapped: &MappedFixture) -> Vec<(usize, [f64; 2])> {
+        let fixt = match self.fixts.get(&mapped.fixt_id) {
+            Some(f) => f,
+            None => return Vec::new(),
+        };
+        let cells = self.library.mode(fixt.type_id, fixt.mode).map(|m| m.cells.as_slice());
+        match cells {
+            Some(cells) if !fixt.cells.is_empty() => {
+                let (s, c) = mapped.rotation.to_radians().sin_cos();
+                fixt.cells
+                    .iter()
+                    .zip(cells)
+                    .map(|(id, cell)| {
+                        let [x, y] = cell.position.map(|p| p * mapped.cell_size);
+                        (*id, [mapped.position[0] + c * x - s * y, mapped.position[1] + s * x + c * y])
+                    })
+                    .collect()
+            }
+            _ => vec![(mapped.fixt_id, mapped.position)],
+        }
+    }
+
+    /// Samples the enabled pixel maps `t` seconds after start and writes the colours to the mapped
+    /// fixtures. Pixels outside of the layout are left out and channels no map wrote are released.
+    /// Images start loading when first rendered and play once they are decoded.
+    pub fn render_pixel_maps(&mut self, t: f64) {
+        let mut pixels: Vec<(usize, Rgba)> = Vec::new();
+        for map in self.pixel_maps.iter_mut().filter(|m| m.enabled) {
+            if let PixelSource::Image(image) = &mut map.source {
+                if !image.is_loaded() && !image.path.is_empty() {
+                    if let Some(frames) = self.images.frames(&image.path) {
+                        image.frames = frames;
+                        image.started = None;
+                    }
+                }
+                if image.is_loaded() {
+                    image.started.get_or_insert(t);
+                }
+            }
+        }
+        let maps = &self.pixel_maps;
+        self.images
+            .retain(|path| maps.iter().any(|m| matches!(&m.source, PixelSource::Image(i) if i.path == path)));
+        for map in self.pixel_maps.iter().filter(|m| m.enabled) {
+            let [w, h] = map.size.map(|s| s.max(f64::EPSILON));
+            for mapped in &map.fixts {
+                pixels.extend(
+                    self.pixel_positions(mapped)
+                        .into_iter()
+                        .map(|(id, [x, y])| (id, [x / w, y / h]))
+                        .filter(|(_, [u, v])| (0. ..=1.).contains(u) && (0. ..=1.).contains(v))
+                        .map(|(id, [u, v])| (id, map.source.sample(u, v, t))),
+                );
+            }
+        }
+        for (fixt_id, color) in pixels {
+            if let Err(e) = self.set_attr(Source::PixelMap, fixt_id, Attribute::Color(color)) {
+                log::trace!("{e}");
+            }
+        }
+        for un in self.universes.values_mut() {
+            un.release_stale(Source::PixelMap);
+        }
+    }
+}
+
+/// Position along a direction through the center of the layout, 0..=1 at the edges for 0 and
+/// 90 degrees
+fn along(u: f64, v: f64, angle: f64) -> f64 {
+    let (s, c) = angle.to_radians().sin_cos();
+    (u - 0.5) * c + (v - 0.5) * s + 0.5
+}
+
+fn mix(a: Rgba, b: Rgba, f: f64) -> Rgba {
+    let mut out = a.0;
+    for (o, b) in out.iter_mut().zip(b.0) {
+        *o += (b - *o) * f;
+    }
+    Rgba(out)
+}
+
+/// Fully saturated colour of a hue (0..1)
+fn hue(h: f64) -> Rgba {
+    let channel = |offset: f64| {
+        let k = (h * 6. + offset).rem_euclid(6.);
+        1. - (k.min(4. - k).clamp(0., 1.))
+    };
+    Rgba([channel(5.), channel(3.), channel(1.), 1.])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dmx::Fixture;
+
+    const RED: Rgba = Rgba([1., 0., 0., 1.]);
+    const BLUE: Rgba = Rgba([0., 0., 1., 1.]);
+
+    #[test]
+    fn buffers_interpolate_between_pixel_centers() {
+        let b = ColorBuffer {
+            width: 2,
+            height: 1,
+            pixels: vec![RED, BLUE],
+        };
+        assert_eq!(b.sample(0.25, 0.5), RED);
+        assert_eq!(b.sample(0.75, 0.5), BLUE);
+        assert_eq!(b.sample(0.5, 0.5), Rgba([0.5, 0., 0.5, 1.]));
+        // Edges and coordinates outside of the buffer keep the outer pixels
+        assert_eq!(b.sample(0., 0.), RED);
+        assert_eq!(b.sample(2., 1.), BLUE);
+        assert_eq!(ColorBuffer::new(0, 0).sample(0.5, 0.5), Rgba([0., 0., 0., 1.]));
+    }
+
+    #[test]
+    fn generators_follow_their_direction() {
+        let g = Generator::Gradient {
+            from: RED,
+            to: BLUE,
+            angle: 90.,
+        };
+        assert_eq!(g.color_at(0.3, 0., 0.), RED);
+        assert_eq!(g.color_at(0.3, 1., 0.), BLUE);
+        let r = Generator::Rainbow {
+            angle: 0.,
+            scale: 1.,
+            speed: 0.5,
+        };
+        assert_eq!(r.color_at(0., 0., 0.), RED);
+        // One cycle takes two seconds
+        let (a, b) = (r.color_at(0.2, 0., 2.), r.color_at(0.2, 0., 0.));
+        assert!(a.0.iter().zip(b.0).all(|(a, b)| (a - b).abs() < 1e-9), "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn cells_are_placed_around_the_fixture() {
+        let mut d = DmxState::new(1);
+        let id = d.add_fixture(Fixture::new("Matrix", 1, 6, 0, 0, 0));
+        let mut mapped = MappedFixture::new(id, [4., 4.]);
+        mapped.cell_size = 2.;
+        let positions = d.pixel_positions(&mapped);
+        assert_eq!(positions.len(), 25);
+        assert_eq!(positions[0], (d.fixts[&id].cells[0], [4., 4.]));
+        assert_eq!(positions[1].1, [6., 4.]);
+        assert_eq!(positions[5].1, [4., 6.]);
+
+        // Clockwise quarter turn, the row runs down
+        mapped.rotation = 90.;
+        let [x, y] = d.pixel_positions(&mapped)[1].1;
+        assert!((x - 4.).abs() < 1e-9 && (y - 6.).abs() < 1e-9);
+
+        let dimmer = d.add_fixture(Fixture::new("Dimmer", 2, 0, 0, 0, 100));
+        assert_eq!(d.pixel_positions(&MappedFixture::new(dimmer, [1., 2.])), vec![(dimmer, [1., 2.])]);
+    }
+
+    #[test]
+    fn disabled_maps_release_their_fixtures() {
+        let mut d = DmxState::new(1);
+        let id = d.add_fixture(Fixture::new("Matrix", 1, 6, 0, 0, 0));
+        let mut map = PixelMap::new("Wall");
+        map.size = [5., 5.];
+        map.fixts.push(MappedFixture::new(id, [0.5, 0.5]));
+        map.source = PixelSource::Generator(Generator::Solid(RED));
+        d.pixel_maps.push(map);
+
+        d.render_pixel_maps(0.);
+        let un = &d.universes[&0];
+        assert_eq!(un.frame()[..6], [255, 0, 0, 255, 0, 0]);
+        assert_eq!(un.attribution(0).map(|a| a.source), Some(Source::PixelMap));
+
+        d.pixel_maps[0].enabled = false;
+        d.render_pixel_maps(0.1);
+        assert_eq!(d.universes[&0].frame()[..6], [0; 6]);
+    }
+}
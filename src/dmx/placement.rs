@@ -0,0 +1,194 @@
+use super::{ChannelPurpose, DmxState, Fixture, Source};
+use crate::error::DmGuiError;
+use serde::{Deserialize, Serialize};
+
+/// Where a moving head hangs and how its pan/tilt is calibrated.
+///
+/// Stage coordinates are in metres with x across the stage, y towards upstage and z up. Without
+/// rotation the fixture stands on its base with the beam at pan 0 and tilt 0 pointing up, pan
+/// turns around its z axis and positive tilt moves the beam towards y (at pan 0).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Placement {
+    pub position: [f64; 3],
+    /// Rotation of the mount around the x, y and z axis in degrees, applied in this order.
+    /// A fixture hanging from a truss is rotated 180° around x.
+    pub rotation: [f64; 3],
+    /// Degrees sent for a mechanical angle of 0
+    pub pan_offset: f64,
+    pub tilt_offset: f64,
+    pub pan_invert: bool,
+    pub tilt_invert: bool,
+    /// Overrides of the pan/tilt ranges of the fixture mode
+    pub pan_range: Option<f64>,
+    pub tilt_range: Option<f64>,
+}
+
+impl Placement {
+    /// Direction of a stage vector in the frame of the fixture
+    pub fn to_local(&self, v: [f64; 3]) -> [f64; 3] {
+        let [rx, ry, rz] = self.rotation;
+        let v = rotate(v, 2, -rz);
+        let v = rotate(v, 1, -ry);
+        rotate(v, 0, -rx)
+    }
+
+    /// Stage direction of the beam at mechanical pan/tilt angles in degrees
+    pub fn beam_direction(&self, pan: f64, tilt: f64) -> [f64; 3] {
+        let (p, t) = (pan.to_radians(), tilt.to_radians());
+        let local = [t.sin() * p.sin(), t.sin() * p.cos(), t.cos()];
+        let [rx, ry, rz] = self.rotation;
+        let v = rotate(local, 0, rx);
+        let v = rotate(v, 1, ry);
+        rotate(v, 2, rz)
+    }
+
+    /// Pan/tilt degrees to send (calibration applied) for the beam to hit `target`, `None` if it
+    /// is out of the ranges. Of the possible solutions the one with the pan closest to `current`
+    /// (or to the center) is taken, so fixtures don't swing around when following a point.
+    pub fn aim(&self, target: [f64; 3], pan_range: f64, tilt_range: f64, current: Option<f64>) -> Option<(f64, f64)> {
+        let [x, y, z] = self.position;
+        let v = [target[0] - x, target[1] - y, target[2] - z];
+        let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+        if len < f64::EPSILON {
+            return None;
+        }
+        let [lx, ly, lz] = self.to_local([v[0] / len, v[1] / len, v[2] / len]);
+        let tilt = lz.clamp(-1., 1.).acos().to_degrees();
+        let pan = lx.atan2(ly).to_degrees();
+
+        let sign = |invert: bool| if invert { -1. } else { 1. };
+        let candidates = [(pan, tilt), (pan + 180., -tilt)];
+        candidates
+            .iter()
+            .flat_map(|(p, t)| [-360., 0., 360.].map(|turn| (p + turn, *t)))
+            .map(|(p, t)| {
+                (
+                    sign(self.pan_invert) * p + self.pan_offset,
+                    sign(self.tilt_invert) * t + self.tilt_offset,
+                )
+            })
+            .filter(|(p, t)| p.abs() <= pan_range / 2. && t.abs() <= tilt_range / 2.)
+            .min_by(|(a, _), (b, _)| {
+                let c = current.unwrap_or(0.);
+                (a - c).abs().total_cmp(&(b - c).abs())
+            })
+    }
+}
+
+/// Rotates `v` around axis 0 (x), 1 (y) or 2 (z) by `deg` degrees
+fn rotate(v: [f64; 3], axis: usize, deg: f64) -> [f64; 3] {
+    let (s, c) = deg.to_radians().sin_cos();
+    let [x, y, z] = v;
+    match axis {
+        0 => [x, c * y - s * z, s * y + c * z],
+        1 => [c * x + s * z, y, -s * x + c * z],
+        _ => [c * x - s * y, s * x + c * y, z],
+    }
+}
+
+impl DmxState {
+    /// Pan/tilt range of a fixture in degrees, its placement can override the one of the mode
+    pub fn degree_range(&self, fixt: &Fixture, purpose: ChannelPurpose) -> Option<f64> {
+        let placed = fixt.placement.as_ref().and_then(|p| match purpose {
+            ChannelPurpose::Pan => p.pan_range,
+            ChannelPurpose::Tilt => p.tilt_range,
+            _ => None,
+        });
+        placed.or_else(|| self.fixture_mode(fixt)?.degree_range(purpose))
+    }
+
+    /// Points a placed fixture at a point in stage coordinates
+    pub fn point_at(&mut self, src: Source, fixt_id: usize, target: [f64; 3]) -> Result<(), DmGuiError> {
+        let fixt = self
+            .fixts
+            .get(&fixt_id)
+            .ok_or_else(|| DmGuiError::dmx(format!("fixture {fixt_id} does not exist")))?;
+        let placement = fixt
+            .placement
+            .as_ref()
+            .ok_or_else(|| DmGuiError::dmx(format!("fixture {fixt_id} has no placement")))?;
+        let range = |p| {
+            self.degree_range(fixt, p)
+                .ok_or_else(|| DmGuiError::dmx(format!("fixture {fixt_id} has no channel for {p:?}")))
+        };
+        let (pan_range, tilt_range) = (range(ChannelPurpose::Pan)?, range(ChannelPurpose::Tilt)?);
+        let current = self.fixture_degrees(fixt_id, ChannelPurpose::Pan);
+        let (pan, tilt) = placement.aim(target, pan_range, tilt_range, current).ok_or_else(|| {
+            DmGuiError::dmx(format!("fixture {fixt_id} can't reach {target:?}"))
+        })?;
+        self.set_fixture_degrees(src, fixt_id, ChannelPurpose::Pan, pan)?;
+        self.set_fixture_degrees(src, fixt_id, ChannelPurpose::Tilt, tilt)
+    }
+
+    /// Points all placed fixtures at one point and returns how many could reach it
+    pub fn refocus(&mut self, src: Source, target: [f64; 3]) -> usize {
+        let placed: Vec<usize> = self
+            .fixts
+            .iter()
+            .filter(|(_, f)| f.placement.is_some())
+            .map(|(id, _)| *id)
+            .collect();
+        placed
+            .into_iter()
+            .filter(|id| match self.point_at(src, *id, target) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::debug!("{e}");
+                    false
+                }
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_hits(p: &Placement, target: [f64; 3]) {
+        let (pan, tilt) = p.aim(target, 540., 270., None).expect("target out of range");
+        let sign = |invert: bool| if invert { -1. } else { 1. };
+        let dir = p.beam_direction(
+            sign(p.pan_invert) * (pan - p.pan_offset),
+            sign(p.tilt_invert) * (tilt - p.tilt_offset),
+        );
+        let v: Vec<f64> = (0..3).map(|i| target[i] - p.position[i]).collect();
+        let len = v.iter().map(|c| c * c).sum::<f64>().sqrt();
+        for i in 0..3 {
+            assert!((dir[i] - v[i] / len).abs() < 1e-9, "{dir:?} doesn't point at {target:?}");
+        }
+    }
+
+    #[test]
+    fn aim_hits_the_target() {
+        assert_eq!(Placement::default().aim([0., 0., 5.], 540., 270., None), Some((0., 0.)));
+        let standing = Placement {
+            position: [1., 1., 0.],
+            ..Default::default()
+        };
+        let hanging = Placement {
+            position: [2., 1., 6.],
+            rotation: [180., 0., 30.],
+            pan_offset: 10.,
+            tilt_invert: true,
+            ..Default::default()
+        };
+        for target in [[0., 0., 0.], [4., -3., 1.], [2., 8., 0.], [-5., 1., 6.]] {
+            assert_hits(&standing, target);
+            assert_hits(&hanging, target);
+        }
+    }
+
+    #[test]
+    fn aim_stays_close_to_the_current_pan() {
+        let p = Placement::default();
+        // Straight towards -y can be reached at pan 180 or -180
+        let (pan, _) = p.aim([0., -1., 0.], 540., 270., Some(170.)).unwrap();
+        assert!((pan - 180.).abs() < 1e-9);
+        let (pan, _) = p.aim([0., -1., 0.], 540., 270., Some(-170.)).unwrap();
+        assert!((pan + 180.).abs() < 1e-9);
+        // Out of range in both directions
+        assert_eq!(p.aim([0., 0., -1.], 540., 90., None), None);
+        assert_eq!(p.aim([0., 0., 0.], 540., 270., None), None);
+    }
+}
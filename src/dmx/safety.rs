@@ -0,0 +1,392 @@
+use super::{combine_value, ChannelPurpose, DmxState, ShutterState, UNIVERSE_SIZE};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Window flash rates are measured over
+const WINDOW: Duration = Duration::from_secs(1);
+/// Holds of the same fixture less than this apart belong to the same intervention
+const EPISODE_GAP: Duration = Duration::from_secs(1);
+
+/// Limits of the photosensitive safety limiter. A flash is a pair of opposing intensity changes
+/// of at least `threshold`, as in common broadcast and web guidelines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SafetyConfig {
+    pub enabled: bool,
+    /// Flashes per second a single fixture may make, strobe effects are slowed down to it
+    pub max_flash_hz: f64,
+    /// Flashes per second of the rig when at least `area_share` of the fixtures flash together
+    pub max_area_flash_hz: f64,
+    /// Share of the patched fixtures (0..=1) making up a large area
+    pub area_share: f64,
+    /// Intensity change (0..=1) counting as half a flash
+    pub threshold: f64,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_flash_hz: 4.,
+            max_area_flash_hz: 3.,
+            area_share: 0.25,
+            threshold: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FixtureFlashes {
+    /// Last intensity let through
+    level: f64,
+    /// Channel values carrying `level`, written back while a change is held
+    held: Vec<(usize, u8)>,
+    /// Peak or trough of the current movement and whether it is rising, a movement of at least
+    /// the threshold against it is the next half flash
+    extreme: f64,
+    rising: Option<bool>,
+    transitions: VecDeque<Instant>,
+    /// Last time a change was held
+    last_hold: Option<Instant>,
+}
+
+/// Output stage analysing the intensity and strobe of every fixture over time. Changes that would
+/// flash faster than allowed are held back, strobe rates are clamped.
+#[derive(Debug, Clone, Default)]
+pub struct SafetyLimiter {
+    pub config: SafetyConfig,
+    /// Number of interventions since start. Holding a fixture or the rig, or slowing a strobe,
+    /// counts once until it wasn't needed for a second.
+    pub interventions: u64,
+    fixts: HashMap<usize, FixtureFlashes>,
+    area_transitions: VecDeque<Instant>,
+    area_last_hold: Option<Instant>,
+    /// Last time the strobe of a fixture was slowed down
+    strobes: HashMap<usize, Instant>,
+}
+
+/// Intensity of a fixture in a frame with the channels carrying it
+struct Sample {
+    fixt_id: usize,
+    universe_id: usize,
+    level: f64,
+    chans: Vec<(usize, u8)>,
+}
+
+impl FixtureFlashes {
+    fn new(sample: &Sample) -> Self {
+        Self {
+            level: sample.level,
+            held: sample.chans.clone(),
+            extreme: sample.level,
+            rising: None,
+            transitions: VecDeque::new(),
+            last_hold: None,
+        }
+    }
+
+    /// Whether `level` reverses the current movement by at least `threshold`. Changes in the
+    /// direction of the movement only move its extreme.
+    fn reverses(&self, level: f64, threshold: f64) -> bool {
+        let up = level > self.extreme;
+        self.rising != Some(up) && (level - self.extreme).abs() >= threshold
+    }
+
+    /// Lets `sample` through, `transition` if it reversed the movement
+    fn follow(&mut self, sample: Sample, transition: bool, now: Instant) {
+        if transition {
+            self.transitions.push_back(now);
+            self.rising = Some(sample.level > self.extreme);
+            self.extreme = sample.level;
+        } else if self.rising.is_some_and(|up| (sample.level > self.extreme) == up) {
+            self.extreme = sample.level;
+        }
+        self.level = sample.level;
+        self.held = sample.chans;
+    }
+}
+
+/// Whether a hold at `now` starts a new intervention after the last one at `last`
+fn new_episode(last: &mut Option<Instant>, now: Instant) -> bool {
+    let new = last.is_none_or(|t| now - t > EPISODE_GAP);
+    *last = Some(now);
+    new
+}
+
+impl SafetyLimiter {
+    fn process(&mut self, dmx_state: &DmxState, frames: &mut [(usize, Vec<u8>)], now: Instant) {
+        if !self.config.enabled {
+            self.fixts.clear();
+            return;
+        }
+        self.limit_strobes(dmx_state, frames, now);
+
+        let samples: Vec<Sample> = frames
+            .iter()
+            .flat_map(|(universe_id, frame)| Self::samples(dmx_state, *universe_id, frame))
+            .collect();
+        let cfg = self.config.clone();
+        let reversing: Vec<bool> = samples
+            .iter()
+            .map(|s| self.fixts.get(&s.fixt_id).is_some_and(|f| f.reverses(s.level, cfg.threshold)))
+            .collect();
+
+        // Many fixtures reversing at once count as a flash of a large area
+        let reversed = reversing.iter().filter(|r| **r).count();
+        let mut hold_all = false;
+        if reversed > 0 && reversed as f64 >= samples.len() as f64 * cfg.area_share {
+            prune(&mut self.area_transitions, now);
+            if too_fast(self.area_transitions.len(), cfg.max_area_flash_hz) {
+                hold_all = true;
+                if new_episode(&mut self.area_last_hold, now) {
+                    self.interventions += 1;
+                    log::warn!(
+                        "Safety limiter: held {reversed} fixtures flashing together faster than {} Hz",
+                        cfg.max_area_flash_hz
+                    );
+                }
+            } else {
+                self.area_transitions.push_back(now);
+            }
+        }
+
+        for (sample, reversing) in samples.into_iter().zip(reversing) {
+            let state = self
+                .fixts
+                .entry(sample.fixt_id)
+                .or_insert_with(|| FixtureFlashes::new(&sample));
+            if !reversing {
+                state.follow(sample, false, now);
+                continue;
+            }
+            prune(&mut state.transitions, now);
+            if hold_all || too_fast(state.transitions.len(), cfg.max_flash_hz) {
+                if let Some((_, frame)) = frames.iter_mut().find(|(id, _)| *id == sample.universe_id) {
+                    for (ch, v) in &state.held {
+                        frame[*ch] = *v;
+                    }
+                }
+                if !hold_all && new_episode(&mut state.last_hold, now) {
+                    self.interventions += 1;
+                    log::warn!(
+                        "Safety limiter: held fixture {} flashing faster than {} Hz",
+                        dmx_state.fixts.get(&sample.fixt_id).map(|f| f.number).unwrap_or_default(),
+                        cfg.max_flash_hz
+                    );
+                }
+            } else {
+                state.follow(sample, true, now);
+            }
+        }
+    }
+
+    /// Slows down strobe effects faster than the allowed flash rate. Without a slow enough value
+    /// the shutter is opened, or closed if it can't be opened.
+    fn limit_strobes(&mut self, dmx_state: &DmxState, frames: &mut [(usize, Vec<u8>)], now: Instant) {
+        let max = self.config.max_flash_hz;
+        for (universe_id, frame) in frames.iter_mut() {
+            for (fixt_id, fixt) in dmx_state.fixts.iter().filter(|(_, f)| f.universe_id == *universe_id) {
+                let mode = match dmx_state.fixture_mode(fixt) {
+                    Some(m) => m,
+                    None => continue,
+                };
+                for purpose in [ChannelPurpose::Shutter, ChannelPurpose::Strobe] {
+                    let ch = match mode.offset_of(purpose).map(|o| fixt.start + o) {
+                        Some(ch) if ch < UNIVERSE_SIZE => ch,
+                        _ => continue,
+                    };
+                    let cap = match mode.capability_at(purpose, frame[ch]) {
+                        Some(c) => c,
+                        None => continue,
+                    };
+                    let rate = match ShutterState::from_capability(cap, frame[ch]).and_then(|s| s.rate()) {
+                        Some(rate) if rate > max => rate,
+                        _ => continue,
+                    };
+                    // The fastest rate allowed of all capabilities of the effect
+                    let slowed = (0..=u8::MAX)
+                        .filter_map(|v| {
+                            let c = mode.capability_at(purpose, v).filter(|c| c.name == cap.name)?;
+                            Some((v, ShutterState::from_capability(c, v)?.rate()?))
+                        })
+                        .filter(|(_, r)| *r <= max)
+                        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+                    let (v, to) = match slowed {
+                        Some((v, r)) => (v, format!("{r:.1} Hz")),
+                        None => match ["Open", "Closed"].into_iter().find_map(|n| mode.capability(purpose, n)) {
+                            Some(c) => (c.value(), c.name.to_lowercase()),
+                            None => continue,
+                        },
+                    };
+                    frame[ch] = v;
+                    let mut last = self.strobes.get(fixt_id).copied();
+                    if new_episode(&mut last, now) {
+                        self.interventions += 1;
+                        log::warn!(
+                            "Safety limiter: {} of fixture {} at {rate:.1} Hz is faster than {max} Hz, set to {to}",
+                            cap.name,
+                            fixt.number
+                        );
+                    }
+                    self.strobes.insert(*fixt_id, now);
+                }
+            }
+        }
+    }
+
+    fn samples(dmx_state: &DmxState, universe_id: usize, frame: &[u8]) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        for (fixt_id, fixt) in dmx_state.fixts.iter().filter(|(_, f)| f.universe_id == universe_id) {
+            let mode = match dmx_state.fixture_mode(fixt) {
+                Some(m) => m,
+                None => continue,
+            };
+            let chans: Vec<usize> = dmx_state
+                .intensity_purposes(fixt, mode)
+                .into_iter()
+                .flat_map(|p| mode.value_offsets(p))
+                .map(|o| fixt.start + o)
+                .filter(|ch| *ch < UNIVERSE_SIZE)
+                .collect();
+            if chans.is_empty() {
+                continue;
+            }
+            // The dimmer with its fine channels, or the brightest colour of a virtual intensity
+            let level = match dmx_state.has_virtual_intensity(fixt) {
+                true => chans.iter().map(|ch| frame[*ch] as f64 / 255.).fold(0., f64::max),
+                false => combine_value(&chans.iter().map(|ch| frame[*ch]).collect::<Vec<u8>>()),
+            };
+            samples.push(Sample {
+                fixt_id: *fixt_id,
+                universe_id,
+                level,
+                chans: chans.into_iter().map(|ch| (ch, frame[ch])).collect(),
+            });
+        }
+        samples
+    }
+}
+
+/// Drops the changes older than the measuring window
+fn prune(transitions: &mut VecDeque<Instant>, now: Instant) {
+    while transitions.front().is_some_and(|t| now - *t > WINDOW) {
+        transitions.pop_front();
+    }
+}
+
+/// Whether one more change in the window makes more than `max_hz` flashes, two changes being one
+fn too_fast(transitions: usize, max_hz: f64) -> bool {
+    (transitions + 1) as f64 / 2. > max_hz * WINDOW.as_secs_f64()
+}
+
+impl DmxState {
+    /// Passes the output frames of all universes through the safety limiter and keeps them as
+    /// [`DmxState::sent_frames`]. Called once per output frame, as the limiter follows the
+    /// fixtures over time.
+    pub fn limit_output(&mut self, frames: &mut [(usize, Vec<u8>)], now: Instant) {
+        let mut limiter = std::mem::take(&mut self.safety);
+        limiter.process(self, frames, now);
+        self.safety = limiter;
+        self.sent_frames = frames.iter().cloned().collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dmx::Fixture;
+
+    const FRAME: Duration = Duration::from_micros(16_667);
+
+    /// Sends `values` to channel 0 frame by frame and returns what the limiter let through
+    fn run(d: &mut DmxState, start: Instant, values: &[u8]) -> Vec<u8> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let mut frames = vec![(0, vec![0; UNIVERSE_SIZE])];
+                frames[0].1[0] = *v;
+                d.limit_output(&mut frames, start + FRAME * i as u32);
+                frames[0].1[0]
+            })
+            .collect()
+    }
+
+    fn dimmers(count: usize) -> DmxState {
+        let mut d = DmxState::new(1);
+        for i in 0..count {
+            d.add_fixture(Fixture::new("Dimmer", i + 1, 0, 0, 0, i));
+        }
+        d
+    }
+
+    #[test]
+    fn flashes_are_counted_in_pairs() {
+        assert!(!too_fast(0, 4.));
+        assert!(!too_fast(7, 4.));
+        assert!(too_fast(8, 4.));
+        assert!(too_fast(1, 0.5));
+    }
+
+    #[test]
+    fn fast_flashes_are_held_once() {
+        let mut d = dimmers(1);
+        // 15 Hz for one second
+        let values: Vec<u8> = (0..60).map(|i| if (i / 2) % 2 == 0 { 255 } else { 0 }).collect();
+        let out = run(&mut d, Instant::now(), &values);
+        let changes = out.windows(2).filter(|w| w[0] != w[1]).count();
+        assert!(changes <= 8, "{changes} changes let through");
+        assert_eq!(d.safety.interventions, 1);
+        assert_eq!(d.sent_frames[&0][0], out[59]);
+    }
+
+    #[test]
+    fn ramps_are_no_flashes() {
+        let mut d = dimmers(1);
+        let up: Vec<u8> = (0..=8).map(|i| i * 30).collect();
+        let values: Vec<u8> = [up.clone(), up.iter().rev().copied().collect()].concat().repeat(2);
+        assert_eq!(run(&mut d, Instant::now(), &values), values);
+        assert_eq!(d.safety.interventions, 0);
+    }
+
+    #[test]
+    fn large_areas_have_a_lower_limit() {
+        let mut d = dimmers(4);
+        let start = Instant::now();
+        let mut let_through = 0;
+        let mut last = 0;
+        for i in 0..60 {
+            let v = if (i / 3) % 2 == 1 { 255 } else { 0 };
+            let mut frames = vec![(0, vec![0; UNIVERSE_SIZE])];
+            frames[0].1[..4].fill(v);
+            d.limit_output(&mut frames, start + FRAME * i);
+            let_through += (frames[0].1[0] != last) as usize;
+            last = frames[0].1[0];
+        }
+        assert!(let_through <= 6, "{let_through} changes let through");
+        assert_eq!(d.safety.interventions, 1);
+    }
+
+    #[test]
+    fn strobes_are_slowed_or_opened() {
+        let mut d = DmxState::new(1);
+        let id = d.add_fixture(Fixture::new("Spot", 1, 4, 0, 0, 0));
+        let shutter = |d: &mut DmxState, at: u32| {
+            let mut frames = vec![(0, vec![0; UNIVERSE_SIZE])];
+            frames[0].1[5] = 95;
+            d.limit_output(&mut frames, Instant::now() + FRAME * at);
+            d.universes.get_mut(&0).unwrap().set(5, frames[0].1[5]);
+            d.shutter(id).unwrap()
+        };
+        match shutter(&mut d, 0) {
+            ShutterState::Strobe(hz) => assert!(hz <= 4. && hz > 3.5, "slowed to {hz} Hz"),
+            s => panic!("{s:?} instead of a slower strobe"),
+        }
+        shutter(&mut d, 1);
+        assert_eq!(d.safety.interventions, 1);
+
+        // The spot strobes at 1 Hz at least
+        d.safety.config.max_flash_hz = 0.5;
+        assert_eq!(shutter(&mut d, 2), ShutterState::Open);
+    }
+}
@@ -0,0 +1,159 @@
+use super::{Capability, ChannelPurpose, DmxState, PhysicalUnit, Source};
+use crate::error::DmGuiError;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Channels shutter states are looked up on, in this order
+const SHUTTER_PURPOSES: &[ChannelPurpose] = &[ChannelPurpose::Shutter, ChannelPurpose::Strobe];
+
+/// State of the shutter independent of the fixture, rates are in Hz. Fixture types provide it by
+/// capabilities named like the variants, with a physical range in Hz for the effects.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ShutterState {
+    Open,
+    Closed,
+    Strobe(f64),
+    Pulse(f64),
+    Random(f64),
+}
+
+impl ShutterState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Open => "Open",
+            Self::Closed => "Closed",
+            Self::Strobe(_) => "Strobe",
+            Self::Pulse(_) => "Pulse",
+            Self::Random(_) => "Random",
+        }
+    }
+
+    pub fn rate(&self) -> Option<f64> {
+        match self {
+            Self::Strobe(hz) | Self::Pulse(hz) | Self::Random(hz) => Some(*hz),
+            Self::Open | Self::Closed => None,
+        }
+    }
+
+    /// State by name (ignoring case), `rate` is used for the effects
+    pub fn from_name(name: &str, rate: f64) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "open" => Some(Self::Open),
+            "closed" => Some(Self::Closed),
+            "strobe" => Some(Self::Strobe(rate)),
+            "pulse" => Some(Self::Pulse(rate)),
+            "random" => Some(Self::Random(rate)),
+            _ => None,
+        }
+    }
+
+    /// State a capability stands for at DMX value `v`
+    pub fn from_capability(cap: &Capability, v: u8) -> Option<Self> {
+        let rate = match &cap.physical {
+            Some(p) if p.unit == PhysicalUnit::Hertz => cap.physical_at(v).unwrap_or(0.),
+            _ => 0.,
+        };
+        Self::from_name(&cap.name, rate)
+    }
+}
+
+impl DmxState {
+    /// Capability providing a shutter state and the channel it is on. Of several capabilities for
+    /// the state the first one whose range contains the rate of the state is taken, the one with
+    /// the closest range if none does.
+    pub fn shutter_capability(&self, fixt_id: usize, state: ShutterState) -> Option<(ChannelPurpose, &Capability)> {
+        let mode = self.fixture_mode(self.fixts.get(&fixt_id)?)?;
+        let mut candidates = SHUTTER_PURPOSES.iter().flat_map(|p| {
+            mode.capabilities(*p)
+                .iter()
+                .filter(|c| c.name.eq_ignore_ascii_case(state.name()))
+                .map(move |c| (*p, c))
+        });
+        match state.rate() {
+            Some(hz) => candidates.min_by(|(_, a), (_, b)| rate_distance(a, hz).total_cmp(&rate_distance(b, hz))),
+            None => candidates.next(),
+        }
+    }
+
+    /// Rates in Hz the capabilities of a fixture for `state` cover together
+    pub fn shutter_rates(&self, fixt_id: usize, state: ShutterState) -> Option<RangeInclusive<f64>> {
+        let mode = self.fixture_mode(self.fixts.get(&fixt_id)?)?;
+        SHUTTER_PURPOSES
+            .iter()
+            .flat_map(|p| mode.capabilities(*p))
+            .filter(|c| c.name.eq_ignore_ascii_case(state.name()))
+            .filter_map(|c| c.physical.as_ref().filter(|p| p.unit == PhysicalUnit::Hertz))
+            .map(|p| (p.from.min(p.to), p.from.max(p.to)))
+            .reduce(|(a0, a1), (b0, b1)| (a0.min(b0), a1.max(b1)))
+            .map(|(from, to)| from..=to)
+    }
+
+    pub fn set_shutter(&mut self, src: Source, fixt_id: usize, state: ShutterState) -> Result<(), DmGuiError> {
+        let (purpose, cap) = self
+            .shutter_capability(fixt_id, state)
+            .ok_or_else(|| DmGuiError::dmx(format!("fixture {fixt_id} has no shutter capability {}", state.name())))?;
+        let v = match (state.rate(), &cap.physical) {
+            (Some(hz), Some(p)) if p.unit == PhysicalUnit::Hertz => cap.value_for(hz),
+            (Some(_), _) => {
+                return Err(DmGuiError::dmx(format!(
+                    "shutter capability {} of fixture {fixt_id} has no rate in Hz",
+                    cap.name
+                )))
+            }
+            (None, _) => cap.value(),
+        };
+        self.set_fixture_raw(src, fixt_id, purpose, v)
+    }
+
+    /// Shutter state selected by the merged value of the first shutter channel naming one
+    pub fn shutter(&self, fixt_id: usize) -> Option<ShutterState> {
+        SHUTTER_PURPOSES.iter().find_map(|p| {
+            let cap = self.capability(fixt_id, *p)?;
+            ShutterState::from_capability(cap, self.fixture_raw(fixt_id, *p)?)
+        })
+    }
+}
+
+/// How far `hz` is outside of the rate range of a capability, 0 inside of it
+fn rate_distance(cap: &Capability, hz: f64) -> f64 {
+    match &cap.physical {
+        Some(p) if p.unit == PhysicalUnit::Hertz => (p.from.min(p.to) - hz).max(hz - p.from.max(p.to)).max(0.),
+        _ => f64::INFINITY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dmx::Fixture;
+
+    #[test]
+    fn rates_pick_the_capability_covering_them() {
+        let mut d = DmxState::new(1);
+        // Spot with a slow and a fast strobe range
+        let spot = d.library.types.get_mut(&4).unwrap();
+        spot.modes[0].capabilities.insert(
+            ChannelPurpose::Shutter,
+            vec![
+                Capability::new("Closed", 0..=31),
+                Capability::new("Strobe", 32..=63).with_physical(PhysicalUnit::Hertz, 1., 5.),
+                Capability::new("Strobe", 64..=95).with_physical(PhysicalUnit::Hertz, 10., 20.),
+                Capability::new("Open", 96..=255),
+            ],
+        );
+        let id = d.add_fixture(Fixture::new("Spot", 1, 4, 0, 0, 0));
+        let range = |state| d.shutter_capability(id, state).map(|(_, c)| c.range.clone().into_inner());
+
+        assert_eq!(range(ShutterState::Strobe(2.)), Some((32, 63)));
+        assert_eq!(range(ShutterState::Strobe(15.)), Some((64, 95)));
+        // Between the ranges the closer one is taken
+        assert_eq!(range(ShutterState::Strobe(6.)), Some((32, 63)));
+        assert_eq!(range(ShutterState::Strobe(9.)), Some((64, 95)));
+        assert_eq!(range(ShutterState::Open), Some((96, 255)));
+        assert_eq!(range(ShutterState::Pulse(5.)), None);
+        assert_eq!(d.shutter_rates(id, ShutterState::Strobe(1.)), Some(1.0..=20.));
+
+        d.set_shutter(Source::Manual, id, ShutterState::Strobe(20.)).unwrap();
+        assert_eq!(d.shutter(id), Some(ShutterState::Strobe(20.)));
+    }
+}
@@ -1,55 +1,174 @@
-use super::{Channel, ChannelMut};
+use super::{Attribution, Layer, LayerStack, MergeMode, Source};
+use crate::color::Rgba;
+use serde::{Deserialize, Serialize};
+
+pub const UNIVERSE_SIZE: usize = 512;
+
+/// Where the output frame of a universe is sent to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputRoute {
+    /// Art-Net port address (net, subnet and universe combined into 15 bits)
+    Artnet { port_address: u16 },
+    Sacn { universe: u16 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UniverseMeta {
+    pub label: String,
+    pub color: Rgba,
+    pub routes: Vec<OutputRoute>,
+}
+
+impl UniverseMeta {
+    pub fn new<S: Into<String>>(label: S) -> Self {
+        Self {
+            label: label.into(),
+            color: Rgba([0.5, 0.5, 0.5, 1.]),
+            routes: Vec::new(),
+        }
+    }
+}
+
+/// Merge settings of a layer as stored in the show
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerConfig {
+    pub source: Source,
+    pub mode: MergeMode,
+    pub priority: u8,
+    pub mask: Vec<bool>,
+}
+
+/// Everything about a universe that is stored in the show, channel values are not
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UniverseConfig {
+    pub meta: UniverseMeta,
+    pub layers: Vec<LayerConfig>,
+}
 
 #[derive(Debug, Clone)]
 pub struct Universe {
-    vals: [u8; 512],
-    vals_ovr: [u8; 512],
-    ovr: [bool; 512],
+    pub meta: UniverseMeta,
+    layers: LayerStack,
+    outp: Vec<u8>,
 }
 
 impl Universe {
-    pub fn new() -> Self {
+    pub fn new(meta: UniverseMeta) -> Self {
         Self {
-            vals: [0; 512],
-            vals_ovr: [0; 512],
-            ovr: [false; 512],
+            meta,
+            layers: LayerStack::new(),
+            outp: vec![0; UNIVERSE_SIZE],
         }
     }
 
-    pub fn get_channel(&self, i: usize) -> Option<Channel> {
-        Some(Channel {
-            val: self.vals.get(i)?,
-            val_ovr: self.vals_ovr.get(i)?,
-            ovr: self.ovr.get(i)?,
-        })
+    pub fn config(&self) -> UniverseConfig {
+        UniverseConfig {
+            meta: self.meta.clone(),
+            layers: self
+                .layers
+                .layers()
+                .iter()
+                .map(|l| LayerConfig {
+                    source: l.source,
+                    mode: l.mode,
+                    priority: l.priority,
+                    mask: l.mask.clone(),
+                })
+                .collect(),
+        }
     }
 
-    pub fn get_channel_mut(&mut self, i: usize) -> Option<ChannelMut> {
-        Some(ChannelMut {
-            val: self.vals.get_mut(i)?,
-            val_ovr: self.vals_ovr.get_mut(i)?,
-            ovr: self.ovr.get_mut(i)?,
-        })
+    pub fn from_config(cfg: UniverseConfig) -> Self {
+        let mut n = Self::new(cfg.meta);
+        for lc in cfg.layers {
+            let mut l = Layer::new(lc.source);
+            l.mode = lc.mode;
+            l.priority = lc.priority;
+            if lc.mask.len() == UNIVERSE_SIZE {
+                l.mask = lc.mask;
+            }
+            n.layers.add_layer(l);
+        }
+        n
     }
 
+    /// Merged value of channel `i`
     pub fn get(&self, i: usize) -> u8 {
-        if self.ovr[i] {
-            self.vals_ovr[i]
-        } else {
-            self.vals[i]
-        }
+        self.outp[i]
+    }
+
+    /// Merged output frame of all layers
+    pub fn frame(&self) -> &[u8] {
+        &self.outp
     }
 
+    /// Sets a channel from the manual sliders
     pub fn set(&mut self, i: usize, n: u8) {
-        self.vals[i] = n;
+        self.set_source(Source::Manual, i, n);
+    }
+
+    pub fn set_source(&mut self, src: Source, i: usize, n: u8) {
+        self.set_source_by(src, None, i, n);
     }
 
-    pub fn set_ovr(&mut self, i: usize, n: u8) {
-        self.ovr[i] = true;
-        self.vals_ovr[i] = n;
+    /// Like [`Universe::set_source`], but remembers `writer` for the attribution of the channel
+    pub fn set_source_by(&mut self, src: Source, writer: Option<u64>, i: usize, n: u8) {
+        if self.layers.write(src, writer, i, n) {
+            self.outp[i] = self.layers.merge(i);
+        }
+    }
+
+    /// Source that won the merge of channel `i`, `None` if no source contributes to it
+    pub fn attribution(&self, i: usize) -> Option<Attribution> {
+        self.layers.attribution(i)
+    }
+
+    /// Stops `src` from contributing to channel `i`
+    pub fn release(&mut self, src: Source, i: usize) {
+        if self.layers.release(src, i) {
+            self.outp[i] = self.layers.merge(i);
+        }
     }
 
-    pub fn reset_ovr(&mut self, i: usize) {
-        self.ovr[i] = false;
+    /// Releases the channels `src` didn't write since the last call, see
+    /// [`LayerStack::release_stale`]
+    pub fn release_stale(&mut self, src: Source) {
+        for i in self.layers.release_stale(src) {
+            self.outp[i] = self.layers.merge(i);
+        }
+    }
+
+    pub fn release_all(&mut self, src: Source) {
+        for i in 0..UNIVERSE_SIZE {
+            self.layers.release(src, i);
+        }
+        self.compose();
+    }
+
+    pub fn layers(&self) -> &LayerStack {
+        &self.layers
+    }
+
+    pub fn add_layer(&mut self, new: Layer) {
+        self.layers.add_layer(new);
+        self.compose();
+    }
+
+    pub fn remove_layer(&mut self, src: Source) -> Option<Layer> {
+        let removed = self.layers.remove_layer(src);
+        self.compose();
+        removed
+    }
+
+    pub fn configure_layer(&mut self, src: Source, f: impl FnOnce(&mut Layer)) {
+        self.layers.configure(src, f);
+        self.compose();
+    }
+
+    /// Recomputes the whole output frame
+    pub fn compose(&mut self) {
+        for i in 0..UNIVERSE_SIZE {
+            self.outp[i] = self.layers.merge(i);
+        }
     }
 }
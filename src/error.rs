@@ -3,6 +3,8 @@ pub enum DmGuiError {
     DMX(String),
     Evaluation(String),
     Networking(String),
+    Serialization(String),
+    Io(String),
 }
 
 impl DmGuiError {
@@ -15,6 +17,12 @@ impl DmGuiError {
     pub fn networking<S: Into<String>>(s: S) -> Self {
         Self::Networking(s.into())
     }
+    pub fn serialization<S: Into<String>>(s: S) -> Self {
+        Self::Serialization(s.into())
+    }
+    pub fn io<S: Into<String>>(s: S) -> Self {
+        Self::Io(s.into())
+    }
 }
 
 use std::fmt;
@@ -24,6 +32,8 @@ impl fmt::Display for DmGuiError {
             Self::DMX(s) => write!(f, "DMX Error: {s}"),
             Self::Evaluation(s) => write!(f, "DMX Error: {s}"),
             Self::Networking(s) => write!(f, "DMX Error: {s}"),
+            Self::Serialization(s) => write!(f, "Serialization Error: {s}"),
+            Self::Io(s) => write!(f, "IO Error: {s}"),
         }
     }
 }
@@ -39,3 +49,9 @@ impl From<artnet_protocol::Error> for DmGuiError {
         Self::Networking(format!("Error with using artnet: {e}"))
     }
 }
+
+impl From<serde_json::Error> for DmGuiError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::serialization(format!("Error with (de)serializing json: {e}"))
+    }
+}
@@ -1,4 +1,7 @@
 use super::port::*;
+use crate::color::Rgba;
+use crate::dmx::{Attribute, ChannelPurpose, ColorBuffer, Generator, ImageSource, MasterControl, PixelSource};
+use crate::dmx::{ShutterState, Target};
 use crate::error::DmGuiError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -20,6 +23,10 @@ impl Node {
     //pub fn nodeargs(&self) -> NodeArgs {
     //    NodeArgs::default() // TODO
     //}
+
+    pub fn template(&self) -> &NodeTempl {
+        &self.node_inst
+    }
 }
 
 #[derive(Clone)]
@@ -27,6 +34,22 @@ pub enum Driver {
     None,
     Func(fn(&HashMap<&'static str, GVal>, Vec<GVal>) -> Result<Vec<GVal>, DmGuiError>),
     Ctx(fn(&HashMap<&'static str, GVal>, Vec<GVal>) -> Result<Vec<(usize, GVal)>, DmGuiError>),
+    /// Produces fixture attributes as `(target, attribute)`
+    Attr(fn(&HashMap<&'static str, GVal>, Vec<GVal>) -> Result<Vec<(Target, Attribute)>, DmGuiError>),
+    /// Operates the masters
+    Control(fn(&HashMap<&'static str, GVal>, Vec<GVal>) -> Result<Vec<MasterControl>, DmGuiError>),
+    /// Sets the sources of pixel maps as `(pixel map name, source)`
+    Pixels(fn(&HashMap<&'static str, GVal>, Vec<GVal>) -> Result<Vec<(String, PixelSource)>, DmGuiError>),
+}
+
+/// Target of the fixture nodes, a non empty group name takes precedence over the fixture id
+fn target(fixt: GVal, group: GVal) -> Result<Target, DmGuiError> {
+    let group = group.as_text()?;
+    if group.trim().is_empty() {
+        Ok(Target::Fixture(fixt.as_inum()? as usize))
+    } else {
+        Ok(Target::Group(group))
+    }
 }
 
 impl fmt::Debug for Driver {
@@ -34,6 +57,9 @@ impl fmt::Debug for Driver {
         match self {
             Self::Func(_) => write!(f, "Driver::Func(<fn omitted>)"),
             Self::Ctx(_) => write!(f, "Driver::Ctx(<fn omitted>)"),
+            Self::Attr(_) => write!(f, "Driver::Attr(<fn omitted>)"),
+            Self::Control(_) => write!(f, "Driver::Control(<fn omitted>)"),
+            Self::Pixels(_) => write!(f, "Driver::Pixels(<fn omitted>)"),
             Self::None => write!(f, "Driver::None: <THIS SHOULD NOT BE HERE>"),
         }
     }
@@ -58,6 +84,17 @@ pub enum NodeTempl {
     Time,
     Output,
     Sine,
+    FixtureIntensity,
+    FixtureColor,
+    FixturePosition,
+    FixtureCapability,
+    FixturePoint,
+    FixtureShutter,
+    Master,
+    Blackout,
+    PixelMap,
+    PixelImage,
+    PixelBuffer,
 }
 
 // A trait for the node kinds, which tells the library how to build new nodes
@@ -73,6 +110,17 @@ impl egui_node_graph::NodeTemplateTrait for NodeTempl {
             Self::Frame => "Frame since start",
             Self::Sine => "Sine",
             Self::Time => "Time",
+            Self::FixtureIntensity => "Fixture intensity",
+            Self::FixtureColor => "Fixture color",
+            Self::FixturePosition => "Fixture pan/tilt",
+            Self::FixtureCapability => "Fixture capability",
+            Self::FixturePoint => "Fixture point at",
+            Self::FixtureShutter => "Fixture shutter",
+            Self::Master => "Master",
+            Self::Blackout => "Blackout",
+            Self::PixelMap => "Pixel map",
+            Self::PixelImage => "Pixel map image",
+            Self::PixelBuffer => "Pixel map buffer",
         }
     }
 
@@ -114,28 +162,192 @@ impl egui_node_graph::NodeTemplateTrait for NodeTempl {
             Self::Output => Node {
                 title: "Output",
                 driver: Driver::Ctx(|_props, mut inp| {
-                    let universe = graph_values::gval_as_inum(inp.remove(0), Some(0..=i64::MAX))?;
-                    let channels = graph_values::gval_as_ivec(inp.remove(0), Some(0..=255))?;
+                    let channels = inp.remove(0).as_ivec()?;
+                    let universe = inp.remove(0).as_inum()?;
+                    if universe < 0 {
+                        return Err(DmGuiError::evaluation(format!("{universe} is no universe")));
+                    }
 
-                    Ok(vec![(
-                        universe as usize,
-                        GVal::IVec(channels.clone(), 0..=255),
-                    )])
+                    Ok(vec![(universe as usize, GVal::IVec(channels, 0..=255))])
                 }),
                 node_inst: NodeTempl::Output,
                 inp: vec![],
             },
             Self::Sine => Node {
                 title: "Sine",
-                driver: Driver::Func(|props, _| {
-                    Ok(vec![props
-                        .get("")
-                        .ok_or_else(|| {
-                            DmGuiError::evaluation("property Frame could not be extracted")
-                        })?
-                        .clone()])
+                driver: Driver::Func(|_props, mut inp| {
+                    let x = inp.remove(0).as_fnum()?;
+                    Ok(vec![GVal::FNum(x.sin(), -1.0..=1.0)])
                 }),
-                node_inst: NodeTempl::Time,
+                node_inst: NodeTempl::Sine,
+                inp: vec![],
+            },
+            Self::FixtureIntensity => Node {
+                title: "Fixture intensity",
+                driver: Driver::Attr(|_props, mut inp| {
+                    let target = target(inp.remove(0), inp.remove(0))?;
+                    let intensity = inp.remove(0).as_fnum()?;
+                    Ok(vec![(target, Attribute::Intensity(intensity))])
+                }),
+                node_inst: NodeTempl::FixtureIntensity,
+                inp: vec![],
+            },
+            Self::FixtureColor => Node {
+                title: "Fixture color",
+                driver: Driver::Attr(|_props, mut inp| {
+                    let target = target(inp.remove(0), inp.remove(0))?;
+                    let color = inp.remove(0).as_color()?;
+                    Ok(vec![(target, Attribute::Color(color))])
+                }),
+                node_inst: NodeTempl::FixtureColor,
+                inp: vec![],
+            },
+            Self::FixturePosition => Node {
+                title: "Fixture pan/tilt",
+                driver: Driver::Attr(|_props, mut inp| {
+                    let target = target(inp.remove(0), inp.remove(0))?;
+                    let pan = inp.remove(0).as_fnum()?;
+                    let tilt = inp.remove(0).as_fnum()?;
+                    Ok(vec![(target.clone(), Attribute::Pan(pan)), (target, Attribute::Tilt(tilt))])
+                }),
+                node_inst: NodeTempl::FixturePosition,
+                inp: vec![],
+            },
+            Self::FixtureCapability => Node {
+                title: "Fixture capability",
+                driver: Driver::Attr(|_props, mut inp| {
+                    let target = target(inp.remove(0), inp.remove(0))?;
+                    let channel = inp.remove(0).as_text()?;
+                    let purpose = ChannelPurpose::from_name(&channel).ok_or_else(|| {
+                        DmGuiError::evaluation(format!("{channel} is not a channel purpose"))
+                    })?;
+                    let name = inp.remove(0).as_text()?;
+                    Ok(vec![(
+                        target,
+                        Attribute::Capability {
+                            purpose,
+                            name,
+                            physical: None,
+                        },
+                    )])
+                }),
+                node_inst: NodeTempl::FixtureCapability,
+                inp: vec![],
+            },
+            Self::FixturePoint => Node {
+                title: "Fixture point at",
+                driver: Driver::Attr(|_props, mut inp| {
+                    let target = target(inp.remove(0), inp.remove(0))?;
+                    let x = inp.remove(0).as_fnum()?;
+                    let y = inp.remove(0).as_fnum()?;
+                    let z = inp.remove(0).as_fnum()?;
+                    Ok(vec![(target, Attribute::Point([x, y, z]))])
+                }),
+                node_inst: NodeTempl::FixturePoint,
+                inp: vec![],
+            },
+            Self::FixtureShutter => Node {
+                title: "Fixture shutter",
+                driver: Driver::Attr(|_props, mut inp| {
+                    let target = target(inp.remove(0), inp.remove(0))?;
+                    let name = inp.remove(0).as_text()?;
+                    let rate = inp.remove(0).as_fnum()?;
+                    let state = ShutterState::from_name(&name, rate).ok_or_else(|| {
+                        DmGuiError::evaluation(format!("{name} is not a shutter state"))
+                    })?;
+                    Ok(vec![(target, Attribute::Shutter(state))])
+                }),
+                node_inst: NodeTempl::FixtureShutter,
+                inp: vec![],
+            },
+            Self::Master => Node {
+                title: "Master",
+                driver: Driver::Control(|_props, mut inp| {
+                    let submaster = inp.remove(0).as_text()?;
+                    let level = inp.remove(0).as_fnum()?;
+                    let flash = inp.remove(0).as_inum()? != 0;
+                    // Without a submaster name the node controls the grand master
+                    if submaster.trim().is_empty() {
+                        Ok(vec![MasterControl::Grand(level), MasterControl::GrandFlash(flash)])
+                    } else {
+                        Ok(vec![
+                            MasterControl::Submaster(submaster.clone(), level),
+                            MasterControl::SubmasterFlash(submaster, flash),
+                        ])
+                    }
+                }),
+                node_inst: NodeTempl::Master,
+                inp: vec![],
+            },
+            Self::Blackout => Node {
+                title: "Blackout",
+                driver: Driver::Control(|_props, mut inp| {
+                    let blackout = inp.remove(0).as_inum()?;
+                    Ok(vec![MasterControl::Blackout(blackout != 0)])
+                }),
+                node_inst: NodeTempl::Blackout,
+                inp: vec![],
+            },
+            Self::PixelMap => Node {
+                title: "Pixel map",
+                driver: Driver::Pixels(|_props, mut inp| {
+                    let map = inp.remove(0).as_text()?;
+                    let name = inp.remove(0).as_text()?;
+                    let (a, b) = (inp.remove(0).as_color()?, inp.remove(0).as_color()?);
+                    let angle = inp.remove(0).as_fnum()?;
+                    let scale = inp.remove(0).as_fnum()?;
+                    let speed = inp.remove(0).as_fnum()?;
+                    let generator = match Generator::from_name(&name) {
+                        Some(Generator::Solid(_)) => Generator::Solid(a),
+                        Some(Generator::Gradient { .. }) => Generator::Gradient { from: a, to: b, angle },
+                        Some(Generator::Rainbow { .. }) => Generator::Rainbow { angle, scale, speed },
+                        Some(Generator::Wave { .. }) => Generator::Wave {
+                            color: a,
+                            background: b,
+                            angle,
+                            scale,
+                            speed,
+                        },
+                        None => return Err(DmGuiError::evaluation(format!("{name} is not a pixel generator"))),
+                    };
+                    Ok(vec![(map, PixelSource::Generator(generator))])
+                }),
+                node_inst: NodeTempl::PixelMap,
+                inp: vec![],
+            },
+            Self::PixelImage => Node {
+                title: "Pixel map image",
+                driver: Driver::Pixels(|_props, mut inp| {
+                    let map = inp.remove(0).as_text()?;
+                    let mut image = ImageSource::new(inp.remove(0).as_text()?);
+                    image.speed = inp.remove(0).as_fnum()?;
+                    image.looping = inp.remove(0).as_inum()? != 0;
+                    image.fps = inp.remove(0).as_fnum()?;
+                    Ok(vec![(map, PixelSource::Image(image))])
+                }),
+                node_inst: NodeTempl::PixelImage,
+                inp: vec![],
+            },
+            Self::PixelBuffer => Node {
+                title: "Pixel map buffer",
+                driver: Driver::Pixels(|_props, mut inp| {
+                    let map = inp.remove(0).as_text()?;
+                    let width = inp.remove(0).as_inum()?;
+                    let values = inp.remove(0).as_fvec()?;
+                    if width < 1 {
+                        return Err(DmGuiError::evaluation(format!("{width} is no buffer width")));
+                    }
+                    // Red, green and blue of each pixel, row by row, an incomplete last row is dropped
+                    let width = width as usize;
+                    let height = values.len() / 3 / width;
+                    let pixels = values
+                        .chunks_exact(3)
+                        .take(width * height)
+                        .map(|c| Rgba([c[0], c[1], c[2], 1.]))
+                        .collect();
+                    Ok(vec![(map, PixelSource::Buffer(ColorBuffer { width, height, pixels }))])
+                }),
+                node_inst: NodeTempl::PixelBuffer,
                 inp: vec![],
             },
         }
@@ -151,9 +363,9 @@ impl egui_node_graph::NodeTemplateTrait for NodeTempl {
                 graph.add_input_param(
                     node_id,
                     $name.to_string(),
-                    GType::INum($range),
+                    GType::INum(*$range.start(), *$range.end()),
                     GVal::INum(0, $range),
-                    eng::InputParamKind::ConnectionOrConstant,
+                    egui_node_graph::InputParamKind::ConnectionOrConstant,
                     !($stat),
                 );
             };
@@ -187,25 +399,37 @@ impl egui_node_graph::NodeTemplateTrait for NodeTempl {
                 graph.add_input_param(
                     node_id,
                     $name.to_string(),
-                    GType::FVec($range),
+                    GType::FVec(*$range.start(), *$range.end()),
                     GVal::FVec(Vec::new(), $range),
-                    eng::InputParamKind::ConnectionOrConstant,
+                    egui_node_graph::InputParamKind::ConnectionOrConstant,
                     !($stat),
                 );
             };
             (fvec $name:expr, $range:expr) => {input!(fvec $name, $range, false)};
 
-            (color $name:expr, $range:expr, $stat:expr) => {
+            (color $name:expr, $stat:expr) => {
                 graph.add_input_param(
                     node_id,
                     $name.to_string(),
                     GType::Color,
-                    GVal::Color(color::Rgba::default(), $range),
-                    eng::InputParamKind::ConnectionOrConstant,
+                    GVal::Color(crate::color::Rgba::default()),
+                    egui_node_graph::InputParamKind::ConnectionOrConstant,
                     !($stat),
                 );
             };
-            (color $name:expr, $range:expr) => {input!(color $name, $range, false)};
+            (color $name:expr) => {input!(color $name, false)};
+
+            (text $name:expr, $stat:expr) => {
+                graph.add_input_param(
+                    node_id,
+                    $name.to_string(),
+                    GType::Text,
+                    GVal::Text(String::new()),
+                    egui_node_graph::InputParamKind::ConnectionOrConstant,
+                    !($stat),
+                );
+            };
+            (text $name:expr) => {input!(text $name, false)};
         }
 
         macro_rules! output {
@@ -233,14 +457,78 @@ impl egui_node_graph::NodeTemplateTrait for NodeTempl {
             }
             Self::Output => {
                 input!(ivec "channels", 0..=255, true);
-                input!(ivec "Universe", 0..=512, false);
+                input!(i "Universe", 0..=i64::MAX);
             }
             Self::Time => {
                 output!(f "", 0.0..=f64::INFINITY);
             }
             Self::Sine => {
                 input!(f "x", 0.0..=f64::INFINITY);
-                output!(f "sin", 0.0..=f64::INFINITY);
+                output!(f "sin", -1.0..=1.0);
+            }
+            Self::FixtureIntensity => {
+                input!(i "fixture", 0..=i64::MAX, true);
+                input!(text "group", true);
+                input!(f "intensity", 0.0..=1.0);
+            }
+            Self::FixtureColor => {
+                input!(i "fixture", 0..=i64::MAX, true);
+                input!(text "group", true);
+                input!(color "color");
+            }
+            Self::FixturePosition => {
+                input!(i "fixture", 0..=i64::MAX, true);
+                input!(text "group", true);
+                input!(f "pan", -360.0..=360.0);
+                input!(f "tilt", -180.0..=180.0);
+            }
+            Self::FixtureCapability => {
+                input!(i "fixture", 0..=i64::MAX, true);
+                input!(text "group", true);
+                input!(text "channel", true);
+                input!(text "capability");
+            }
+            Self::FixturePoint => {
+                input!(i "fixture", 0..=i64::MAX, true);
+                input!(text "group", true);
+                input!(f "x", -100.0..=100.0);
+                input!(f "y", -100.0..=100.0);
+                input!(f "z", -100.0..=100.0);
+            }
+            Self::FixtureShutter => {
+                input!(i "fixture", 0..=i64::MAX, true);
+                input!(text "group", true);
+                input!(text "state", true);
+                input!(f "rate", 0.0..=50.0);
+            }
+            Self::Master => {
+                input!(text "submaster", true);
+                input!(f "level", 0.0..=1.0);
+                input!(i "flash", 0..=1);
+            }
+            Self::Blackout => {
+                input!(i "blackout", 0..=1);
+            }
+            Self::PixelMap => {
+                input!(text "pixel map", true);
+                input!(text "generator", true);
+                input!(color "color a");
+                input!(color "color b");
+                input!(f "angle", 0.0..=360.0);
+                input!(f "scale", 0.0..=16.0);
+                input!(f "speed", -10.0..=10.0);
+            }
+            Self::PixelImage => {
+                input!(text "pixel map", true);
+                input!(text "path", true);
+                input!(f "speed", -10.0..=10.0);
+                input!(i "loop", 0..=1);
+                input!(f "fps", 0.1..=120.0);
+            }
+            Self::PixelBuffer => {
+                input!(text "pixel map", true);
+                input!(i "width", 1..=1024, true);
+                input!(fvec "pixels", 0.0..=1.0);
             }
         }
     }
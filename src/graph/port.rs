@@ -9,6 +9,7 @@ pub enum GType {
     Color,
     FVec(f64, f64),
     IVec(i64, i64),
+    Text,
 }
 
 /// This is for being able to connect different types into the same connections, to compare the two
@@ -30,6 +31,7 @@ impl GType {
             (Color, Color) => true,
             (FVec(smin, smax), FVec(omin, omax)) => smin == omin && smax == omax,
             (IVec(smin, smax), IVec(omin, omax)) => smin == omin && smax == omax,
+            (Text, Text) => true,
             (_, _) => false,
         }
     }
@@ -50,6 +52,7 @@ pub enum GVal {
     Color(Rgba),
     FVec(Vec<f64>, RangeInclusive<f64>),
     IVec(Vec<i64>, RangeInclusive<i64>),
+    Text(String),
 }
 
 impl From<&GType> for GVal {
@@ -60,6 +63,7 @@ impl From<&GType> for GVal {
             GType::Color => Self::Color(Default::default()),
             GType::FVec(rmin, rmax) => Self::FVec(Default::default(), *rmin..=*rmax),
             GType::IVec(rmin, rmax) => Self::IVec(Default::default(), *rmin..=*rmax),
+            GType::Text => Self::Text(String::new()),
         }
     }
 }
@@ -72,6 +76,7 @@ impl From<&GVal> for GType {
             GVal::Color(_) => Self::Color,
             GVal::FVec(_, range) => Self::FVec(*range.start(), *range.end()),
             GVal::IVec(_, range) => Self::IVec(*range.start(), *range.end()),
+            GVal::Text(_) => Self::Text,
         }
     }
 }
@@ -103,6 +108,7 @@ impl GVal {
                 },
             ]
             .into()),
+            Self::Text(_) => Err(DmGuiError::evaluation("Text is not convertable to a Color")),
         }
     }
 
@@ -111,8 +117,12 @@ impl GVal {
             Self::FNum(n, r) => Ok(n),
             Self::INum(n, r) => Ok(n as f64),
             Self::Color(c) => Err(DmGuiError::evaluation("Color is not convertable to a Num")),
-            Self::FVec(v, r) => Ok(v[0]),
-            Self::IVec(v, r) => Ok(v[0] as f64),
+            Self::FVec(v, r) => v.first().copied().ok_or_else(|| DmGuiError::evaluation("Empty list is not a Num")),
+            Self::IVec(v, r) => v
+                .first()
+                .map(|n| *n as f64)
+                .ok_or_else(|| DmGuiError::evaluation("Empty list is not a Num")),
+            Self::Text(_) => Err(DmGuiError::evaluation("Text is not convertable to a Num")),
         }
     }
 
@@ -121,8 +131,12 @@ impl GVal {
             Self::FNum(n, r) => Ok(n as i64),
             Self::INum(n, r) => Ok(n),
             Self::Color(c) => Err(DmGuiError::evaluation("Color is not convertable to a Num")),
-            Self::FVec(v, r) => Ok(v[0] as i64),
-            Self::IVec(v, r) => Ok(v[0]),
+            Self::FVec(v, r) => v
+                .first()
+                .map(|n| *n as i64)
+                .ok_or_else(|| DmGuiError::evaluation("Empty list is not a Num")),
+            Self::IVec(v, r) => v.first().copied().ok_or_else(|| DmGuiError::evaluation("Empty list is not a Num")),
+            Self::Text(_) => Err(DmGuiError::evaluation("Text is not convertable to a Num")),
         }
     }
     pub fn as_fvec(self) -> Result<Vec<f64>, DmGuiError> {
@@ -132,6 +146,7 @@ impl GVal {
             Self::Color(c) => Ok(c.0.into_iter().collect()),
             Self::FVec(v, r) => Ok(v),
             Self::IVec(v, r) => Ok(v.into_iter().map(|c| c as f64).collect()),
+            Self::Text(_) => Err(DmGuiError::evaluation("Text is not convertable to a list")),
         }
     }
     pub fn as_ivec(self) -> Result<Vec<i64>, DmGuiError> {
@@ -145,6 +160,16 @@ impl GVal {
                 .collect()),
             Self::FVec(v, r) => Ok(v.into_iter().map(|c| c as i64).collect()),
             Self::IVec(v, r) => Ok(v),
+            Self::Text(_) => Err(DmGuiError::evaluation("Text is not convertable to a list")),
+        }
+    }
+
+    pub fn as_text(self) -> Result<String, DmGuiError> {
+        match self {
+            Self::FNum(n, _) => Ok(n.to_string()),
+            Self::INum(n, _) => Ok(n.to_string()),
+            Self::Text(s) => Ok(s),
+            o => Err(DmGuiError::evaluation(format!("{o:?} is not convertable to Text"))),
         }
     }
 }
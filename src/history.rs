@@ -0,0 +1,356 @@
+use crate::{
+    app_graph::NodeGraphType,
+    dmx::{Fixture, Source, UniverseSnapshot},
+    TronomicState,
+};
+use std::collections::VecDeque;
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphEdit {
+    CreateNode,
+    DeleteNode,
+    Connect,
+    Disconnect,
+    Value,
+}
+
+/// A single undoable edit. Every command stores both sides of the change, so it can be applied in
+/// either direction.
+#[derive(Debug, Clone)]
+pub enum Command {
+    Graph {
+        edit: GraphEdit,
+        before: Box<NodeGraphType>,
+        after: Box<NodeGraphType>,
+    },
+    Patch {
+        fixt_id: usize,
+        before: Option<Box<Fixture>>,
+        after: Option<Box<Fixture>>,
+    },
+    /// Value of a channel in the manual layer, `None` while the layer doesn't contribute to it
+    Channel {
+        universe: usize,
+        channel: usize,
+        before: Option<u8>,
+        after: Option<u8>,
+    },
+    /// Manual virtual intensity of a fixture without a dimmer, `None` if it was released
+    VirtualIntensity {
+        fixt_id: usize,
+        before: Option<f64>,
+        after: Option<f64>,
+    },
+    /// Adding or removing a universe
+    Universe {
+        id: usize,
+        before: Option<Box<UniverseSnapshot>>,
+        after: Option<Box<UniverseSnapshot>>,
+    },
+    Batch(Vec<Command>),
+}
+
+impl Command {
+    fn merges_with(&self, newer: &Command) -> bool {
+        match (self, newer) {
+            (
+                Self::Graph { edit: GraphEdit::Value, .. },
+                Self::Graph { edit: GraphEdit::Value, .. },
+            ) => true,
+            (
+                Self::Patch { fixt_id: a, before: Some(_), after: Some(_) },
+                Self::Patch { fixt_id: b, before: Some(_), after: Some(_) },
+            ) => a == b,
+            (
+                Self::Channel { universe: ua, channel: ca, .. },
+                Self::Channel { universe: ub, channel: cb, .. },
+            ) => ua == ub && ca == cb,
+            (Self::VirtualIntensity { fixt_id: a, .. }, Self::VirtualIntensity { fixt_id: b, .. }) => a == b,
+            // Editing several fixtures at once, e.g. dragging a curve of the whole selection
+            (Self::Batch(a), Self::Batch(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.merges_with(b)),
+            (_, _) => false,
+        }
+    }
+
+    fn merge(&mut self, newer: Command) {
+        match (self, newer) {
+            (Self::Graph { after, .. }, Self::Graph { after: n, .. }) => *after = n,
+            (Self::Patch { after, .. }, Self::Patch { after: n, .. }) => *after = n,
+            (Self::Channel { after, .. }, Self::Channel { after: n, .. }) => *after = n,
+            (Self::VirtualIntensity { after, .. }, Self::VirtualIntensity { after: n, .. }) => *after = n,
+            (Self::Batch(cmds), Self::Batch(n)) => cmds.iter_mut().zip(n).for_each(|(c, n)| c.merge(n)),
+            (_, _) => (),
+        }
+    }
+
+    fn apply(&self, state: &TronomicState, forward: bool) {
+        match self {
+            Self::Graph { before, after, .. } => {
+                let target = if forward { after } else { before };
+                *state.graph.write() = (**target).clone();
+            }
+            Self::Patch { fixt_id, before, after } => {
+                let target = if forward { after } else { before };
+                state.dmx_state.write().set_fixture(*fixt_id, target.as_deref().cloned());
+            }
+            Self::Channel { universe, channel, before, after } => {
+                let target = if forward { after } else { before };
+                if let Some(un) = state.dmx_state.write().universes.get_mut(universe) {
+                    match target {
+                        Some(v) => un.set(*channel, *v),
+                        None => un.release(Source::Manual, *channel),
+                    }
+                }
+            }
+            Self::VirtualIntensity { fixt_id, before, after } => {
+                let target = if forward { after } else { before };
+                let mut dmx_state = state.dmx_state.write();
+                match target {
+                    Some(v) => {
+                        if let Err(e) = dmx_state.set_virtual_intensity(Source::Manual, *fixt_id, *v) {
+                            log::debug!("{e}");
+                        }
+                    }
+                    None => dmx_state.virtual_intensity.release(Source::Manual, *fixt_id),
+                }
+            }
+            Self::Universe { id, before, after } => {
+                let target = if forward { after } else { before };
+                state.dmx_state.write().set_universe(*id, target.as_deref().cloned());
+            }
+            Self::Batch(cmds) => {
+                if forward {
+                    cmds.iter().for_each(|c| c.apply(state, true));
+                } else {
+                    cmds.iter().rev().for_each(|c| c.apply(state, false));
+                }
+            }
+        }
+    }
+}
+
+/// Bounded undo/redo stack. Consecutive commands touching the same thing are merged into one step
+/// until [`History::end_merge`] is called, which the GUI does whenever no pointer button is held.
+#[derive(Debug, Clone)]
+pub struct History {
+    limit: usize,
+    undo: VecDeque<Command>,
+    redo: Vec<Command>,
+    merging: bool,
+    graph_base: Option<Box<NodeGraphType>>,
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            merging: false,
+            graph_base: None,
+        }
+    }
+
+    pub fn push(&mut self, cmd: Command) {
+        self.redo.clear();
+        if self.merging {
+            if let Some(last) = self.undo.back_mut() {
+                if last.merges_with(&cmd) {
+                    last.merge(cmd);
+                    return;
+                }
+            }
+        }
+        self.undo.push_back(cmd);
+        self.merging = true;
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn end_merge(&mut self) {
+        self.merging = false;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo(&mut self, state: &TronomicState) -> bool {
+        self.merging = false;
+        if let Some(cmd) = self.undo.pop_back() {
+            cmd.apply(state, false);
+            if let Command::Graph { before, .. } = &cmd {
+                self.graph_base = Some(before.clone());
+            }
+            self.redo.push(cmd);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn redo(&mut self, state: &TronomicState) -> bool {
+        self.merging = false;
+        if let Some(cmd) = self.redo.pop() {
+            cmd.apply(state, true);
+            if let Command::Graph { after, .. } = &cmd {
+                self.graph_base = Some(after.clone());
+            }
+            self.undo.push_back(cmd);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Compares the graph against the last recorded snapshot and records a command if it changed.
+    /// Structural edits are reported by the editor, changed constant values are detected here.
+    pub fn track_graph(&mut self, graph: &NodeGraphType, edit: Option<GraphEdit>) {
+        let base = match self.graph_base.as_ref() {
+            Some(base) => base,
+            None => {
+                self.graph_base = Some(Box::new(graph.clone()));
+                return;
+            }
+        };
+        let edit = edit.or_else(|| {
+            graph
+                .graph
+                .inputs
+                .iter()
+                .any(|(id, inp)| base.graph.inputs.get(id).map(|b| &b.value) != Some(&inp.value))
+                .then_some(GraphEdit::Value)
+        });
+
+        if let Some(edit) = edit {
+            if let Some(before) = self.graph_base.take() {
+                self.record_graph(edit, before, graph);
+            }
+        }
+    }
+
+    /// Records a graph change made outside of the editor, e.g. pasting nodes.
+    pub fn record_graph(&mut self, edit: GraphEdit, before: Box<NodeGraphType>, graph: &NodeGraphType) {
+        let after = Box::new(graph.clone());
+        self.graph_base = Some(after.clone());
+        self.push(Command::Graph { edit, before, after });
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(channel: usize, before: Option<u8>, after: u8) -> Command {
+        Command::Channel {
+            universe: 0,
+            channel,
+            before,
+            after: Some(after),
+        }
+    }
+
+    /// `(before, after)` of the channel commands on the undo stack, oldest first
+    fn steps(h: &History) -> Vec<(Option<u8>, Option<u8>)> {
+        h.undo
+            .iter()
+            .map(|cmd| match cmd {
+                Command::Channel { before, after, .. } => (*before, *after),
+                cmd => panic!("{cmd:?} is no channel command"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn drags_merge_into_one_step() {
+        let mut h = History::default();
+        h.push(channel(0, None, 1));
+        for v in 2..=10 {
+            h.push(channel(0, Some(v - 1), v));
+        }
+        assert_eq!(steps(&h), [(None, Some(10))]);
+        // Another channel is a step of its own
+        h.push(channel(1, Some(3), 5));
+        assert_eq!(steps(&h), [(None, Some(10)), (Some(3), Some(5))]);
+    }
+
+    #[test]
+    fn end_merge_splits_steps() {
+        let mut h = History::default();
+        h.push(channel(0, None, 1));
+        h.end_merge();
+        h.push(channel(0, Some(1), 2));
+        h.push(channel(0, Some(2), 3));
+        assert_eq!(steps(&h), [(None, Some(1)), (Some(1), Some(3))]);
+    }
+
+    #[test]
+    fn oldest_steps_are_evicted_at_the_limit() {
+        let mut h = History::new(3);
+        for v in 1..=5 {
+            h.push(channel(0, Some(v - 1), v));
+            h.end_merge();
+        }
+        assert_eq!(steps(&h), [(Some(2), Some(3)), (Some(3), Some(4)), (Some(4), Some(5))]);
+    }
+
+    #[test]
+    fn pushing_clears_redo() {
+        let mut h = History::default();
+        h.push(channel(0, None, 1));
+        h.end_merge();
+        // As left behind by undoing a step
+        h.redo.push(channel(0, Some(1), 2));
+        assert!(h.can_redo());
+        h.push(channel(1, None, 1));
+        assert!(!h.can_redo());
+        assert_eq!(steps(&h), [(None, Some(1)), (None, Some(1))]);
+    }
+
+    fn patch(fixt_id: usize, start: usize) -> Command {
+        let fixt = |start| Some(Box::new(Fixture::new("Dimmer", 1, 0, 0, 0, start)));
+        Command::Patch {
+            fixt_id,
+            before: fixt(0),
+            after: fixt(start),
+        }
+    }
+
+    #[test]
+    fn batches_of_the_same_fixtures_merge() {
+        let mut h = History::default();
+        for start in 1..10 {
+            h.push(Command::Batch(vec![patch(0, start), patch(1, start)]));
+        }
+        assert_eq!(h.undo.len(), 1);
+        match &h.undo[0] {
+            Command::Batch(cmds) => match &cmds[1] {
+                Command::Patch { before, after, .. } => {
+                    assert_eq!(before.as_ref().map(|f| f.start), Some(0));
+                    assert_eq!(after.as_ref().map(|f| f.start), Some(9));
+                }
+                _ => panic!("batch contains {:?}", cmds[1]),
+            },
+            cmd => panic!("{cmd:?} is no batch"),
+        }
+
+        h.push(Command::Batch(vec![patch(0, 1), patch(2, 1)]));
+        assert_eq!(h.undo.len(), 2);
+        h.end_merge();
+        h.push(Command::Batch(vec![patch(0, 1), patch(2, 1)]));
+        assert_eq!(h.undo.len(), 3);
+    }
+}
@@ -1,7 +1,4 @@
-use crate::{
-    error::DmGuiError,
-    dmx::Universe,
-};
+use crate::error::DmGuiError;
 use artnet_protocol as ap;
 use std::net::{UdpSocket, ToSocketAddrs, IpAddr, SocketAddr};
 use serde::{Serialize, Deserialize};
@@ -38,8 +35,8 @@ impl ArtnetConnection {
         Ok(())
     }
 
-    pub fn send_universe(&self, universe_id: usize, un: Universe) -> Result<(), DmGuiError> {
-        let data = (0..255).map(|i| *un.get_channel(i).unwrap().get()).collect::<Vec<u8>>().into();
+    pub fn send_universe(&self, universe_id: usize, frame: &[u8]) -> Result<(), DmGuiError> {
+        let data = frame.to_vec().into();
         let cmd = ap::ArtCommand::Output(ap::Output {
             data,
             ..ap::Output::default()
@@ -71,6 +68,56 @@ impl ArtnetConnection {
     }
 }
 
+pub const ARTNET_PORT: u16 = 6454;
+
+/// Sends ArtDmx packets to `target`, the broadcast address of the local network by default
+#[derive(Debug)]
+pub struct ArtnetSender {
+    pub target: SocketAddr,
+    sock: Option<UdpSocket>,
+    sequence: HashMap<u16, u8>,
+}
+
+impl ArtnetSender {
+    pub fn new() -> Self {
+        Self {
+            target: SocketAddr::from(([255, 255, 255, 255], ARTNET_PORT)),
+            sock: None,
+            sequence: HashMap::new(),
+        }
+    }
+}
+
+impl Default for ArtnetSender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl super::UniverseSender for ArtnetSender {
+    fn send(&mut self, port_address: u16, frame: &[u8]) -> Result<(), DmGuiError> {
+        if self.sock.is_none() {
+            let sock = UdpSocket::bind(("0.0.0.0", 0))?;
+            sock.set_broadcast(true)?;
+            self.sock = Some(sock);
+        }
+        // 0 means the receiver doesn't check the order, so it is skipped
+        let sequence = self.sequence.entry(port_address).or_insert(0);
+        *sequence = sequence.checked_add(1).unwrap_or(1);
+        let cmd = ap::ArtCommand::Output(ap::Output {
+            port_address: ap::PortAddress::try_from(port_address)?,
+            sequence: *sequence,
+            data: frame.to_vec().into(),
+            ..ap::Output::default()
+        });
+        let buf = cmd.write_to_buffer()?;
+        if let Some(sock) = self.sock.as_ref() {
+            sock.send_to(&buf, self.target)?;
+        }
+        Ok(())
+    }
+}
+
 /*
 let socket = UdpSocket::bind(("0.0.0.0", 6454)).unwrap();
 let broadcast_addr = ("255.255.255.255", 6454).to_socket_addrs().unwrap().next().unwrap();
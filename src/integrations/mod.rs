@@ -2,8 +2,40 @@ pub mod artnet;
 pub mod sacn;
 pub mod ofl;
 
+/// Network protocol output frames are sent with, see [`crate::dmx::OutputRoute`]
 pub trait UniverseSender {
-    fn send(&self, univ: &crate::dmx::Universe);
+    /// Sends an output frame to `universe`, numbered as the protocol does, see
+    /// [`crate::dmx::DmxState::output_frame`]
+    fn send(&mut self, universe: u16, frame: &[u8]) -> Result<(), crate::error::DmGuiError>;
+}
+
+/// Senders of all protocols, frames are dispatched to them by their routes
+#[derive(Debug)]
+pub struct OutputSenders {
+    pub artnet: artnet::ArtnetSender,
+    pub sacn: sacn::SacnSender,
+}
+
+impl OutputSenders {
+    pub fn new() -> Self {
+        Self {
+            artnet: artnet::ArtnetSender::new(),
+            sacn: sacn::SacnSender::new(env!("CARGO_PKG_NAME")),
+        }
+    }
+
+    pub fn send(&mut self, route: &crate::dmx::OutputRoute, frame: &[u8]) -> Result<(), crate::error::DmGuiError> {
+        match route {
+            crate::dmx::OutputRoute::Artnet { port_address } => self.artnet.send(*port_address, frame),
+            crate::dmx::OutputRoute::Sacn { universe } => self.sacn.send(*universe, frame),
+        }
+    }
+}
+
+impl Default for OutputSenders {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 use slotmap::SlotMap;
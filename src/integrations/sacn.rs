@@ -0,0 +1,122 @@
+use super::UniverseSender;
+use crate::error::DmGuiError;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+
+pub const SACN_PORT: u16 = 5568;
+const DEFAULT_PRIORITY: u8 = 100;
+
+/// Sends E1.31 (sACN) data packets to the multicast address of each universe
+#[derive(Debug)]
+pub struct SacnSender {
+    pub source_name: String,
+    pub priority: u8,
+    cid: [u8; 16],
+    sock: Option<UdpSocket>,
+    sequence: HashMap<u16, u8>,
+}
+
+impl SacnSender {
+    pub fn new<S: Into<String>>(source_name: S) -> Self {
+        // Component identifier, unique enough to tell instances on the network apart
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default()
+            ^ std::process::id() as u128;
+        Self {
+            source_name: source_name.into(),
+            priority: DEFAULT_PRIORITY,
+            cid: seed.to_be_bytes(),
+            sock: None,
+            sequence: HashMap::new(),
+        }
+    }
+
+    /// Multicast group receivers of `universe` listen on
+    pub fn multicast_addr(universe: u16) -> SocketAddr {
+        let [hi, lo] = universe.to_be_bytes();
+        SocketAddr::from((Ipv4Addr::new(239, 255, hi, lo), SACN_PORT))
+    }
+
+    /// Data packet carrying `frame` (at most 512 slots) with the start code 0
+    pub fn packet(&self, universe: u16, sequence: u8, frame: &[u8]) -> Vec<u8> {
+        let slots = &frame[..frame.len().min(512)];
+        let len = 126 + slots.len();
+        let flags_len = |pdu_len: usize| (0x7000 | pdu_len as u16).to_be_bytes();
+
+        let mut p = Vec::with_capacity(len);
+        // Root layer
+        p.extend_from_slice(&0x0010u16.to_be_bytes());
+        p.extend_from_slice(&0u16.to_be_bytes());
+        p.extend_from_slice(b"ASC-E1.17\0\0\0");
+        p.extend_from_slice(&flags_len(len - 16));
+        p.extend_from_slice(&4u32.to_be_bytes());
+        p.extend_from_slice(&self.cid);
+        // Framing layer
+        p.extend_from_slice(&flags_len(len - 38));
+        p.extend_from_slice(&2u32.to_be_bytes());
+        let mut name = [0u8; 64];
+        let name_len = self.source_name.len().min(63);
+        name[..name_len].copy_from_slice(&self.source_name.as_bytes()[..name_len]);
+        p.extend_from_slice(&name);
+        p.push(self.priority.min(200));
+        p.extend_from_slice(&0u16.to_be_bytes());
+        p.push(sequence);
+        p.push(0);
+        p.extend_from_slice(&universe.to_be_bytes());
+        // DMP layer
+        p.extend_from_slice(&flags_len(len - 115));
+        p.push(0x02);
+        p.push(0xa1);
+        p.extend_from_slice(&0u16.to_be_bytes());
+        p.extend_from_slice(&1u16.to_be_bytes());
+        p.extend_from_slice(&(slots.len() as u16 + 1).to_be_bytes());
+        p.push(0);
+        p.extend_from_slice(slots);
+        p
+    }
+}
+
+impl UniverseSender for SacnSender {
+    fn send(&mut self, universe: u16, frame: &[u8]) -> Result<(), DmGuiError> {
+        if !(1..=63999).contains(&universe) {
+            return Err(DmGuiError::networking(format!("{universe} is no valid sACN universe")));
+        }
+        if self.sock.is_none() {
+            self.sock = Some(UdpSocket::bind(("0.0.0.0", 0))?);
+        }
+        let sequence = self.sequence.entry(universe).or_insert(0);
+        *sequence = sequence.wrapping_add(1);
+        let sequence = *sequence;
+        let packet = self.packet(universe, sequence, frame);
+        if let Some(sock) = self.sock.as_ref() {
+            sock.send_to(&packet, Self::multicast_addr(universe))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_layout() {
+        let sender = SacnSender::new("test");
+        let p = sender.packet(0x0102, 7, &[255; 512]);
+        assert_eq!(p.len(), 638);
+        assert_eq!(&p[4..16], b"ASC-E1.17\0\0\0");
+        assert_eq!(p[16..18], (0x7000u16 | 622).to_be_bytes());
+        assert_eq!(p[38..40], (0x7000u16 | 600).to_be_bytes());
+        assert_eq!(&p[44..48], b"test");
+        assert_eq!((p[108], p[111]), (DEFAULT_PRIORITY, 7));
+        assert_eq!(p[113..115], [1, 2]);
+        assert_eq!(p[115..117], (0x7000u16 | 523).to_be_bytes());
+        assert_eq!(p[123..125], 513u16.to_be_bytes());
+        assert_eq!((p[125], p[126], p[637]), (0, 255, 255));
+
+        assert_eq!(sender.packet(1, 0, &[1, 2, 3]).len(), 129);
+        assert_eq!(SacnSender::multicast_addr(0x0102).to_string(), "239.255.1.2:5568");
+    }
+}
@@ -1,5 +1,8 @@
+mod clipboard;
 mod color;
 mod error;
+mod history;
+mod show;
 mod integrations;
 mod threads;
 mod asset;
@@ -129,7 +132,7 @@ async fn main() {
             fps_outp: Arc::new(RwLock::new(0.)),
             time: Arc::new(RwLock::new(Instant::now())),
             frame: Arc::new(RwLock::new(0)),
-            dmx_state: Arc::new(RwLock::new(dmx::DmxState::new(3))),
+            dmx_state: Arc::new(RwLock::new(dmx::DmxState::new(1))),
             connections: Arc::new(RwLock::new(slotmap::SlotMap::with_key())),
             graph: Arc::new(RwLock::new(egui_node_graph::GraphEditorState::new(1., app_graph::MyGraphState::default()))),
         };
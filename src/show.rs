@@ -0,0 +1,109 @@
+use crate::{
+    app_graph::{self, GraphClip, MyGraphState},
+    dmx::{
+        DmxState, Fixture, FixtureGroup, FixtureLibrary, Masters, Modifiers, PixelMap, SafetyConfig, Universe,
+        UniverseConfig, VirtualIntensities,
+    },
+    error::DmGuiError,
+    TronomicState,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+pub const SHOW_VERSION: u32 = 1;
+pub const DEFAULT_SHOW_PATH: &str = "show.json";
+
+/// Everything that makes up a show, stored as json. Channel values are not part of it, the virtual
+/// intensities of fixtures without a dimmer are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Show {
+    pub version: u32,
+    pub universes: BTreeMap<usize, UniverseConfig>,
+    pub library: FixtureLibrary,
+    pub fixts: HashMap<usize, Fixture>,
+    #[serde(default)]
+    pub fixt_groups: HashMap<usize, FixtureGroup>,
+    #[serde(default)]
+    pub modifiers: Modifiers,
+    #[serde(default)]
+    pub masters: Masters,
+    #[serde(default)]
+    pub safety: SafetyConfig,
+    #[serde(default)]
+    pub pixel_maps: Vec<PixelMap>,
+    #[serde(default)]
+    pub graph: GraphClip,
+    #[serde(default)]
+    pub virtual_intensity: VirtualIntensities,
+}
+
+impl Show {
+    pub fn capture(state: &TronomicState) -> Self {
+        let dmx_state = state.dmx_state.read();
+        let graph = state.graph.read();
+        let node_ids: Vec<_> = graph.node_order.clone();
+        Self {
+            version: SHOW_VERSION,
+            universes: dmx_state.universes.iter().map(|(id, un)| (*id, un.config())).collect(),
+            library: dmx_state.library.clone(),
+            fixts: dmx_state.fixts.clone(),
+            fixt_groups: dmx_state.fixt_groups.clone(),
+            modifiers: dmx_state.modifiers.clone(),
+            masters: dmx_state.masters.clone(),
+            safety: dmx_state.safety.config.clone(),
+            pixel_maps: dmx_state.pixel_maps.clone(),
+            graph: app_graph::copy_nodes(&graph, &node_ids),
+            virtual_intensity: {
+                let mut levels = dmx_state.virtual_intensity.clone();
+                levels.retain_fixtures(|id| dmx_state.fixts.contains_key(&id));
+                levels
+            },
+        }
+    }
+
+    /// Replaces the show in `state` with this one
+    pub fn restore(self, state: &TronomicState) {
+        let mut dmx_state = DmxState::new(0);
+        dmx_state.universes = self
+            .universes
+            .into_iter()
+            .map(|(id, cfg)| (id, Universe::from_config(cfg)))
+            .collect();
+        dmx_state.library = self.library;
+        dmx_state.fixt_next_id = self.fixts.keys().map(|id| id + 1).max().unwrap_or(0);
+        dmx_state.fixts = self.fixts;
+        dmx_state.group_next_id = self.fixt_groups.keys().map(|id| id + 1).max().unwrap_or(0);
+        dmx_state.fixt_groups = self.fixt_groups;
+        dmx_state.sync_all_cells();
+        dmx_state.modifiers = self.modifiers;
+        dmx_state.restore_parked();
+        dmx_state.virtual_intensity = self.virtual_intensity;
+        dmx_state.masters = self.masters;
+        dmx_state.safety.config = self.safety;
+        dmx_state.pixel_maps = self.pixel_maps;
+        *state.dmx_state.write() = dmx_state;
+
+        let mut graph = egui_node_graph::GraphEditorState::new(1., MyGraphState::default());
+        app_graph::paste_nodes(&mut graph, &self.graph, egui::Vec2::ZERO);
+        *state.graph.write() = graph;
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), DmGuiError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+            .map_err(|e| DmGuiError::serialization(format!("could not write show to {path}: {e}")))
+    }
+
+    pub fn load(path: &str) -> Result<Self, DmGuiError> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| DmGuiError::serialization(format!("could not read show from {path}: {e}")))?;
+        let show: Self = serde_json::from_str(&json)?;
+        if show.version > SHOW_VERSION {
+            return Err(DmGuiError::serialization(format!(
+                "show version {} is newer than the supported version {SHOW_VERSION}",
+                show.version
+            )));
+        }
+        Ok(show)
+    }
+}
@@ -1,6 +1,11 @@
 use tokio::time::{self, Instant, Duration};
 use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::sync::Arc;
+use egui_node_graph::NodeId;
+use crate::app_graph::{self, Evaluation};
+use crate::dmx::{DmxState, MasterControl, PixelSource, Source, UNIVERSE_SIZE};
+use crate::graph::port::GVal;
 
 pub async fn process_eval(freq: f64, tron_state: crate::TronomicState) -> ! {
     let mut interval = time::interval(Duration::from_secs_f64(1./freq));
@@ -9,14 +14,98 @@ pub async fn process_eval(freq: f64, tron_state: crate::TronomicState) -> ! {
     let graph = tron_state.graph;
     let frame = tron_state.frame;
     let time = tron_state.time;
+    let dmx_state = tron_state.dmx_state;
 
+    let mut controls = HashMap::new();
+    let mut pixels = HashMap::new();
+    let mut errors = HashMap::new();
     loop {
         interval.tick().await;
         let start = Instant::now();
 
+        let props = HashMap::from([
+            ("time", GVal::FNum(time.read().elapsed().as_secs_f64(), 0.0..=f64::INFINITY)),
+            ("frame", GVal::INum(*frame.read(), 0..=i64::MAX)),
+        ]);
+        let eval = app_graph::evaluate(&graph.read().graph, &props);
+        *frame.write() += 1;
+        let mut applied = Applied {
+            controls: &mut controls,
+            pixels: &mut pixels,
+        };
+        apply(&mut dmx_state.write(), eval, &mut applied, &mut errors);
+
         let calc_time = start.elapsed();
         *freq_mon.write() =  1e9/(start-last).as_nanos() as f64;
 
         last = start;
     }
 }
+
+/// Outputs of the nodes operating the masters and pixel maps as last applied
+struct Applied<'a> {
+    controls: &'a mut HashMap<NodeId, Vec<MasterControl>>,
+    pixels: &'a mut HashMap<NodeId, Vec<(String, PixelSource)>>,
+}
+
+/// Writes the results of an evaluation to the DMX state. Channels the graph stopped writing are
+/// released. Master controls and pixel map sources are only applied when they changed since the
+/// last evaluation of their node, so they can still be operated from the GUI in between.
+fn apply(dmx_state: &mut DmxState, eval: Evaluation, applied: &mut Applied, errors: &mut HashMap<NodeId, String>) {
+    let mut failed = eval.errors;
+    for (id, universe, values) in eval.channels {
+        let writer = Some(app_graph::node_writer(id));
+        match dmx_state.universes.get_mut(&universe) {
+            Some(un) => {
+                for (i, v) in values.into_iter().take(UNIVERSE_SIZE).enumerate() {
+                    un.set_source_by(Source::Graph, writer, i, v);
+                }
+            }
+            None => failed.push((id, format!("Universe {universe} does not exist"))),
+        }
+    }
+    for (id, target, attr) in eval.attrs {
+        if let Err(e) = dmx_state.set_attr_target(Source::Graph, &target, attr) {
+            failed.push((id, e.to_string()));
+        }
+    }
+    for un in dmx_state.universes.values_mut() {
+        un.release_stale(Source::Graph);
+    }
+    dmx_state.virtual_intensity.release_stale(Source::Graph);
+
+    applied.controls.retain(|id, _| eval.controls.iter().any(|(c, _)| c == id));
+    for (id, ctls) in eval.controls {
+        let last = applied.controls.get(&id);
+        for (i, ctl) in ctls.iter().enumerate() {
+            if last.and_then(|l| l.get(i)) == Some(ctl) {
+                continue;
+            }
+            if let Err(e) = dmx_state.control(ctl.clone()) {
+                failed.push((id, e.to_string()));
+            }
+        }
+        applied.controls.insert(id, ctls);
+    }
+    applied.pixels.retain(|id, _| eval.pixels.iter().any(|(p, _)| p == id));
+    for (id, sources) in eval.pixels {
+        if applied.pixels.get(&id) == Some(&sources) {
+            continue;
+        }
+        for (name, source) in sources.iter().cloned() {
+            if let Err(e) = dmx_state.set_pixel_source(&name, source) {
+                failed.push((id, e.to_string()));
+            }
+        }
+        applied.pixels.insert(id, sources);
+    }
+
+    // Errors are logged once until they change
+    errors.retain(|id, _| failed.iter().any(|(f, _)| f == id));
+    for (id, msg) in failed {
+        if errors.get(&id) != Some(&msg) {
+            log::warn!("Graph evaluation: {msg}");
+            errors.insert(id, msg);
+        }
+    }
+}
@@ -2,7 +2,8 @@ use tokio::time::{self, Instant, Duration};
 use parking_lot::{Mutex, RwLock};
 use std::sync::Arc;
 use crate::dmx::DmxState;
-use crate::integrations::artnet;
+use crate::dmx::OutputRoute;
+use crate::integrations::{artnet, OutputSenders};
 
 pub async fn output_send(freq: f64, tron_state: crate::TronomicState) -> ! {
     let mut interval = time::interval(Duration::from_secs_f64(1./freq));
@@ -10,13 +11,36 @@ pub async fn output_send(freq: f64, tron_state: crate::TronomicState) -> ! {
     let freq_mon = tron_state.fps_outp;
     let dmx_state = tron_state.dmx_state;
     let conns = tron_state.connections;
+    let time = tron_state.time;
 
     //let artnet_con = artnet::ArtnetConnection::new();
+    let mut senders = OutputSenders::new();
+    let mut last_error = None;
     loop {
         interval.tick().await;
         let start = Instant::now();
 
-        for c in &dmx_state.read().universes {
+        let frames: Vec<(Vec<OutputRoute>, Vec<u8>)> = {
+            let mut dmx_state = dmx_state.write();
+            dmx_state.render_pixel_maps(time.read().elapsed().as_secs_f64());
+            let mut frames = dmx_state.output_frames();
+            dmx_state.limit_output(&mut frames, start.into_std());
+            frames
+                .into_iter()
+                .map(|(id, frame)| (dmx_state.universes[&id].meta.routes.clone(), frame))
+                .collect()
+        };
+        // Sent without holding the lock, errors are logged once until a different one occurs
+        for (routes, frame) in &frames {
+            for route in routes {
+                if let Err(e) = senders.send(route, frame) {
+                    let msg = format!("Sending to {route:?} failed: {e}");
+                    if last_error.as_ref() != Some(&msg) {
+                        log::warn!("{msg}");
+                        last_error = Some(msg);
+                    }
+                }
+            }
         }
 
         let calc_time = start.elapsed();
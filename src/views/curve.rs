@@ -0,0 +1,64 @@
+use crate::dmx::{sample_lut, Curve, LUT_LARGE, LUT_SMALL};
+use std::hash::Hash;
+
+/// Combo box selecting a curve, `None` is shown as `unset`. Lookup tables get an area below to
+/// draw them with the mouse.
+pub fn curve_ui(ui: &mut egui::Ui, id_source: impl Hash, curve: &mut Option<Curve>, unset: &str) {
+    let selected = curve.as_ref().map(|c| c.name()).unwrap_or_else(|| unset.to_string());
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            if ui.selectable_label(curve.is_none(), unset).clicked() {
+                *curve = None;
+            }
+            for preset in Curve::PRESETS {
+                if ui.selectable_label(curve.as_ref() == Some(preset), preset.name()).clicked() {
+                    *curve = Some(preset.clone());
+                }
+            }
+            for len in [LUT_SMALL, LUT_LARGE] {
+                let is_lut = matches!(curve, Some(Curve::Lut(l)) if l.len() == len);
+                if ui.selectable_label(is_lut, format!("LUT ({len} entries)")).clicked() && !is_lut {
+                    let base = curve.clone().unwrap_or_default();
+                    *curve = Some(Curve::Lut(base.to_lut(len)));
+                }
+            }
+        });
+    if let Some(Curve::Lut(lut)) = curve {
+        lut_ui(ui, lut);
+    }
+}
+
+/// Drawing area for a lookup table, dragging sets the entries below the pointer
+fn lut_ui(ui: &mut egui::Ui, lut: &mut [u16]) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(160., 100.), egui::Sense::drag());
+    if lut.len() < 2 {
+        return;
+    }
+
+    if let Some(pos) = response.interact_pointer_pos() {
+        let x = ((pos.x - rect.left()) / rect.width()).clamp(0., 1.) as f64;
+        let y = ((rect.bottom() - pos.y) / rect.height()).clamp(0., 1.) as f64;
+        // One pixel covers several entries of large tables
+        let per_px = (lut.len() as f64 / rect.width() as f64).max(1.);
+        let center = x * (lut.len() - 1) as f64;
+        let from = (center - per_px / 2.).max(0.).round() as usize;
+        let to = ((center + per_px / 2.).round() as usize).min(lut.len() - 1);
+        let v = (y * u16::MAX as f64).round() as u16;
+        lut[from..=to].iter_mut().for_each(|e| *e = v);
+    }
+
+    let painter = ui.painter_at(rect);
+    let visuals = ui.style().visuals.clone();
+    painter.rect_filled(rect, 0., visuals.extreme_bg_color);
+    let points: Vec<egui::Pos2> = (0..=rect.width() as usize)
+        .map(|px| {
+            let x = px as f64 / rect.width() as f64;
+            egui::pos2(
+                rect.left() + px as f32,
+                rect.bottom() - sample_lut(lut, x) as f32 * rect.height(),
+            )
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, visuals.widgets.active.fg_stroke));
+}
@@ -0,0 +1,958 @@
+use crate::dmx::{
+    Attribute, AttributeKind, Capability, Cell, ChannelDefaults, ChannelPurpose, ColorMixing, DmxState, Emitter,
+    Fixture, FixtureMode, FixtureType, Physical, PhysicalUnit, Placement, ShutterState, Source, UNIVERSE_SIZE,
+};
+use super::curve::curve_ui;
+use super::groups::GroupsView;
+use crate::color::Rgb;
+use crate::history::{Command, History};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct FixturesView {
+    pub selection: Vec<usize>,
+    patch_type: usize,
+    patch_mode: usize,
+    patch_universe: usize,
+    /// Address to start patching at, 0 based
+    patch_address: usize,
+    patch_count: usize,
+    groups: GroupsView,
+    /// Group stepped through by the highlight
+    highlight_group: Option<usize>,
+    /// Stage point to aim the selection or the whole rig at
+    point: [f64; 3],
+}
+
+impl Default for FixturesView {
+    fn default() -> Self {
+        Self {
+            selection: Vec::new(),
+            patch_type: 0,
+            patch_mode: 0,
+            patch_universe: 0,
+            patch_address: 0,
+            patch_count: 1,
+            groups: GroupsView::default(),
+            highlight_group: None,
+            point: [0.; 3],
+        }
+    }
+}
+
+impl FixturesView {
+    pub fn ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState, history: &mut History) {
+        self.patch_ui(ui, dmx_state, history);
+        ui.separator();
+
+        if !self.selection.is_empty() {
+            ui.collapsing("Attributes", |ui| self.attributes_ui(ui, dmx_state, history));
+            ui.separator();
+        }
+        ui.collapsing("Highlight", |ui| self.highlight_ui(ui, dmx_state));
+        ui.separator();
+        if !self.selection.is_empty() {
+            ui.collapsing("Output curves", |ui| self.curves_ui(ui, dmx_state, history));
+            ui.collapsing("Placement", |ui| self.placement_ui(ui, dmx_state, history));
+        }
+        ui.collapsing("Groups", |ui| self.groups.ui(ui, dmx_state, &mut self.selection));
+        ui.separator();
+
+        let mut ids: Vec<usize> = dmx_state.fixts.keys().copied().collect();
+        ids.sort_unstable_by_key(|id| {
+            let f = &dmx_state.fixts[id];
+            (f.number, f.cell_of.map(|(_, i)| i + 1).unwrap_or(0))
+        });
+        self.selection.retain(|id| dmx_state.fixts.contains_key(id));
+
+        let conflicts = dmx_state.validate_patch();
+        if !conflicts.is_empty() {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("{} patch problem(s), hover the marked fixtures for details", conflicts.len()),
+            );
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("fixture_patch").striped(true).show(ui, |ui| {
+                ui.label("No.");
+                ui.label("Name");
+                ui.label("Type");
+                ui.label("Universe");
+                ui.label("Address");
+                ui.label("");
+                ui.end_row();
+
+                for fixt_id in ids {
+                    let before = dmx_state.fixts[&fixt_id].clone();
+                    let mut edited = before.clone();
+                    let mut remove = false;
+
+                    let selected = self.selection.contains(&fixt_id);
+                    let number = match edited.cell_of {
+                        Some((_, cell)) => format!("{}.{}", edited.number, cell + 1),
+                        None => edited.number.to_string(),
+                    };
+                    if ui.selectable_label(selected, number).clicked() {
+                        if !ui.input().modifiers.shift {
+                            self.selection = vec![fixt_id];
+                        } else if selected {
+                            self.selection.retain(|id| *id != fixt_id);
+                        } else {
+                            self.selection.push(fixt_id);
+                        }
+                    }
+                    // Cells are patched with their fixture
+                    if edited.cell_of.is_some() {
+                        ui.label(&edited.name);
+                        ui.label("Cell");
+                        ui.label(edited.universe_id.to_string());
+                        ui.label((edited.start + 1).to_string());
+                        ui.end_row();
+                        continue;
+                    }
+                    ui.text_edit_singleline(&mut edited.name);
+                    ui.label(match dmx_state.library.types.get(&edited.type_id) {
+                        Some(t) => match t.modes.get(edited.mode) {
+                            Some(m) => format!("{} ({})", t.name(), m.name),
+                            None => format!("{} (unknown mode)", t.name()),
+                        },
+                        None => "unknown type".to_string(),
+                    });
+                    ui.add(egui::DragValue::new(&mut edited.universe_id));
+                    let mut addr = edited.start + 1;
+                    ui.add(egui::DragValue::new(&mut addr).clamp_range(1..=UNIVERSE_SIZE));
+                    edited.start = addr - 1;
+                    let problems: Vec<String> = conflicts
+                        .iter()
+                        .filter(|c| c.fixtures().contains(&fixt_id))
+                        .map(|c| c.to_string())
+                        .collect();
+                    if problems.is_empty() {
+                        ui.label("");
+                    } else {
+                        ui.colored_label(egui::Color32::RED, "⚠").on_hover_text(problems.join("\n"));
+                    }
+                    if ui.button("Remove").clicked() {
+                        remove = true;
+                    }
+                    ui.end_row();
+
+                    if remove {
+                        dmx_state.remove_fixture(fixt_id);
+                        history.push(Command::Patch {
+                            fixt_id,
+                            before: Some(Box::new(before)),
+                            after: None,
+                        });
+                    } else if edited != before {
+                        dmx_state.set_fixture(fixt_id, Some(edited.clone()));
+                        history.push(Command::Patch {
+                            fixt_id,
+                            before: Some(Box::new(before)),
+                            after: Some(Box::new(edited)),
+                        });
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.collapsing("Fixture library", |ui| {
+                let cells = Self::mode_cells(dmx_state);
+                Self::library_ui(ui, dmx_state);
+                if Self::mode_cells(dmx_state) != cells {
+                    Self::sync_cells(dmx_state, history);
+                }
+            });
+        });
+    }
+
+    fn patch_ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState, history: &mut History) {
+        let mut type_ids: Vec<usize> = dmx_state.library.types.keys().copied().collect();
+        type_ids.sort_unstable();
+        if !dmx_state.library.types.contains_key(&self.patch_type) {
+            self.patch_type = type_ids.first().copied().unwrap_or_default();
+        }
+
+        ui.horizontal(|ui| {
+            let lib = &dmx_state.library;
+            let typ = lib.types.get(&self.patch_type);
+            egui::ComboBox::from_id_source("patch_type")
+                .selected_text(typ.map(|t| t.name()).unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for id in &type_ids {
+                        ui.selectable_value(&mut self.patch_type, *id, lib.types[id].name());
+                    }
+                });
+            let modes = typ.map(|t| t.modes.as_slice()).unwrap_or_default();
+            self.patch_mode = self.patch_mode.min(modes.len().saturating_sub(1));
+            egui::ComboBox::from_id_source("patch_mode")
+                .selected_text(modes.get(self.patch_mode).map(|m| m.name.as_str()).unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for (i, m) in modes.iter().enumerate() {
+                        ui.selectable_value(&mut self.patch_mode, i, &m.name);
+                    }
+                });
+            ui.label("Universe");
+            ui.add(egui::DragValue::new(&mut self.patch_universe));
+
+            ui.label("Address");
+            let mut addr = self.patch_address + 1;
+            ui.add(egui::DragValue::new(&mut addr).clamp_range(1..=UNIVERSE_SIZE));
+            self.patch_address = addr - 1;
+            ui.label("Count");
+            ui.add(egui::DragValue::new(&mut self.patch_count).clamp_range(1..=UNIVERSE_SIZE));
+
+            if ui.add_enabled(!modes.is_empty(), egui::Button::new("Patch")).clicked() {
+                let footprint = modes[self.patch_mode].footprint();
+                self.patch(dmx_state, history, footprint);
+            }
+            if ui
+                .add_enabled(!self.selection.is_empty(), egui::Button::new("Auto-address selection"))
+                .on_hover_text("Address the selected fixtures one after another, starting at the patch address")
+                .clicked()
+            {
+                self.auto_address(dmx_state, history);
+            }
+        });
+    }
+
+    /// Patches `patch_count` fixtures one after another from the patch address on
+    fn patch(&mut self, dmx_state: &mut DmxState, history: &mut History, footprint: usize) {
+        let footprints = vec![footprint; self.patch_count];
+        let addrs = match dmx_state.sequential_addresses(self.patch_universe, self.patch_address, &footprints, &[]) {
+            Ok(addrs) => addrs,
+            Err(e) => {
+                log::warn!("{e}");
+                return;
+            }
+        };
+        let mut cmds = Vec::with_capacity(addrs.len());
+        self.selection.clear();
+        for (universe_id, start) in addrs {
+            let number = dmx_state.next_fixture_number();
+            let new = Fixture::new(
+                format!("{} {number}", dmx_state.library.types[&self.patch_type].model),
+                number,
+                self.patch_type,
+                self.patch_mode,
+                universe_id,
+                start,
+            );
+            let fixt_id = dmx_state.add_fixture(new);
+            self.selection.push(fixt_id);
+            cmds.push(Command::Patch {
+                fixt_id,
+                before: None,
+                after: Some(Box::new(dmx_state.fixts[&fixt_id].clone())),
+            });
+        }
+        history.push(Command::Batch(cmds));
+    }
+
+    /// Re-addresses the selection in the order of the fixture numbers, cells move with their fixture
+    fn auto_address(&mut self, dmx_state: &mut DmxState, history: &mut History) {
+        let mut ids: Vec<usize> = self
+            .selection
+            .iter()
+            .copied()
+            .filter(|id| dmx_state.fixts.get(id).is_some_and(|f| f.cell_of.is_none()))
+            .collect();
+        ids.sort_unstable_by_key(|id| dmx_state.fixts[id].number);
+        let footprints: Vec<usize> = ids.iter().map(|id| dmx_state.footprint(&dmx_state.fixts[id])).collect();
+        let addrs = match dmx_state.sequential_addresses(self.patch_universe, self.patch_address, &footprints, &ids) {
+            Ok(addrs) => addrs,
+            Err(e) => {
+                log::warn!("{e}");
+                return;
+            }
+        };
+        let mut cmds = Vec::with_capacity(ids.len());
+        for (fixt_id, (universe_id, start)) in ids.into_iter().zip(addrs) {
+            let before = dmx_state.fixts[&fixt_id].clone();
+            let after = Fixture {
+                universe_id,
+                start,
+                ..before.clone()
+            };
+            dmx_state.set_fixture(fixt_id, Some(after.clone()));
+            cmds.push(Command::Patch {
+                fixt_id,
+                before: Some(Box::new(before)),
+                after: Some(Box::new(after)),
+            });
+        }
+        history.push(Command::Batch(cmds));
+    }
+
+    /// Cells of all modes of the library by type id and mode index
+    fn mode_cells(dmx_state: &DmxState) -> HashMap<(usize, usize), Vec<Cell>> {
+        dmx_state
+            .library
+            .types
+            .iter()
+            .flat_map(|(type_id, t)| t.modes.iter().enumerate().map(move |(i, m)| ((*type_id, i), m.cells.clone())))
+            .collect()
+    }
+
+    /// Updates the cells of the patched fixtures after their modes changed, the changes are
+    /// recorded as one step
+    fn sync_cells(dmx_state: &mut DmxState, history: &mut History) {
+        let before = dmx_state.fixts.clone();
+        dmx_state.sync_all_cells();
+        let mut ids: Vec<usize> = before.keys().chain(dmx_state.fixts.keys()).copied().collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let cmds: Vec<Command> = ids
+            .into_iter()
+            .filter(|id| before.get(id) != dmx_state.fixts.get(id))
+            .map(|fixt_id| Command::Patch {
+                fixt_id,
+                before: before.get(&fixt_id).cloned().map(Box::new),
+                after: dmx_state.fixts.get(&fixt_id).cloned().map(Box::new),
+            })
+            .collect();
+        if !cmds.is_empty() {
+            history.push(Command::Batch(cmds));
+        }
+    }
+
+    /// Editor for the fixture types, changes apply to all patched instances
+    fn library_ui(ui: &mut egui::Ui, dmx_state: &mut DmxState) {
+        if ui.button("New type").clicked() {
+            dmx_state.library.add_type(FixtureType {
+                manufacturer: "Custom".into(),
+                model: "Fixture".into(),
+                modes: vec![FixtureMode::new("Default", vec![ChannelPurpose::Dimmer])],
+                virtual_intensity: true,
+                color_mixing: ColorMixing::default(),
+            });
+        }
+
+        let mut type_ids: Vec<usize> = dmx_state.library.types.keys().copied().collect();
+        type_ids.sort_unstable();
+        for type_id in type_ids {
+            let typ = dmx_state.library.types.get_mut(&type_id).unwrap();
+            egui::CollapsingHeader::new(typ.name()).id_source(("fixture_type", type_id)).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Manufacturer");
+                    ui.text_edit_singleline(&mut typ.manufacturer);
+                    ui.label("Model");
+                    ui.text_edit_singleline(&mut typ.model);
+                });
+                ui.checkbox(&mut typ.virtual_intensity, "Virtual intensity")
+                    .on_hover_text("Scale the colour channels of modes without a dimmer by the intensity");
+                egui::CollapsingHeader::new("Colour mixing")
+                    .id_source(("color_mixing", type_id))
+                    .show(ui, |ui| Self::color_mixing_ui(ui, type_id, &mut typ.color_mixing));
+                for (mode_i, mode) in typ.modes.iter_mut().enumerate() {
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Mode");
+                            ui.text_edit_singleline(&mut mode.name);
+                        });
+                        let mut remove = None;
+                        for (i, purpose) in mode.channel_purposes.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{}", i + 1));
+                                egui::ComboBox::from_id_source(("mode_channel", type_id, mode_i, i))
+                                    .selected_text(format!("{purpose:?}"))
+                                    .show_ui(ui, |ui| {
+                                        for p in ChannelPurpose::ALL {
+                                            ui.selectable_value(purpose, *p, format!("{p:?}"));
+                                        }
+                                    });
+                                let mut defaults = mode
+                                    .defaults
+                                    .get(purpose)
+                                    .copied()
+                                    .unwrap_or_else(|| ChannelDefaults::of(*purpose));
+                                ui.label("home");
+                                ui.add(egui::DragValue::new(&mut defaults.home));
+                                ui.label("highlight");
+                                ui.add(egui::DragValue::new(&mut defaults.highlight));
+                                if defaults != ChannelDefaults::of(*purpose) {
+                                    mode.defaults.insert(*purpose, defaults);
+                                } else {
+                                    mode.defaults.remove(purpose);
+                                }
+                                if ui.small_button("x").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                        }
+                        let purposes = mode.channel_purposes.clone();
+                        for purpose in purposes.iter().copied().filter(|p| *p != ChannelPurpose::NoType) {
+                            egui::CollapsingHeader::new(format!("{purpose:?} curve and capabilities"))
+                                .id_source(("mode_caps", type_id, mode_i, purpose))
+                                .show(ui, |ui| {
+                                    if !purpose.is_fine() {
+                                        let mut curve = mode.curves.remove(&purpose);
+                                        ui.horizontal(|ui| {
+                                            ui.label("Curve");
+                                            curve_ui(ui, ("mode_curve", type_id, mode_i, purpose), &mut curve, "Linear");
+                                        });
+                                        if let Some(c) = curve.filter(|c| !c.is_linear()) {
+                                            mode.curves.insert(purpose, c);
+                                        }
+                                    }
+                                    Self::capabilities_ui(ui, purpose, mode.capabilities.entry(purpose).or_default())
+                                });
+                        }
+                        mode.capabilities.retain(|p, caps| !caps.is_empty() && purposes.contains(p));
+                        mode.curves.retain(|p, _| purposes.contains(p));
+                        if let Some(i) = remove {
+                            mode.channel_purposes.remove(i);
+                        }
+                        if ui.small_button("Add channel").clicked() {
+                            mode.channel_purposes.push(ChannelPurpose::NoType);
+                        }
+                        egui::CollapsingHeader::new(format!("Cells ({})", mode.cells.len()))
+                            .id_source(("mode_cells", type_id, mode_i))
+                            .show(ui, |ui| Self::cells_ui(ui, (type_id, mode_i), &mut mode.cells));
+                    });
+                }
+                if ui.button("Add mode").clicked() {
+                    typ.modes.push(FixtureMode::new(format!("Mode {}", typ.modes.len() + 1), vec![]));
+                }
+            });
+        }
+    }
+
+    /// Editor for the cells of a mode. New cells repeat the layout of the last one behind it.
+    fn cells_ui(ui: &mut egui::Ui, id: (usize, usize), cells: &mut Vec<Cell>) {
+        let mut remove = None;
+        egui::Grid::new(("cells", id)).striped(true).show(ui, |ui| {
+            ui.label("Name");
+            ui.label("Offset");
+            ui.label("Position");
+            ui.label("Channels");
+            ui.end_row();
+            for (i, cell) in cells.iter_mut().enumerate() {
+                ui.text_edit_singleline(&mut cell.mode.name);
+                let mut offset = cell.offset + 1;
+                ui.add(egui::DragValue::new(&mut offset).clamp_range(1..=UNIVERSE_SIZE));
+                cell.offset = offset - 1;
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut cell.position[0]).speed(0.1).prefix("x "));
+                    ui.add(egui::DragValue::new(&mut cell.position[1]).speed(0.1).prefix("y "));
+                });
+                ui.horizontal(|ui| {
+                    let mut remove_channel = None;
+                    for (ch, purpose) in cell.mode.channel_purposes.iter_mut().enumerate() {
+                        egui::ComboBox::from_id_source(("cell_channel", id, i, ch))
+                            .selected_text(format!("{purpose:?}"))
+                            .show_ui(ui, |ui| {
+                                for p in ChannelPurpose::ALL {
+                                    ui.selectable_value(purpose, *p, format!("{p:?}"));
+                                }
+                                if ui.button("Remove channel").clicked() {
+                                    remove_channel = Some(ch);
+                                }
+                            });
+                    }
+                    if let Some(ch) = remove_channel {
+                        cell.mode.channel_purposes.remove(ch);
+                    }
+                    if ui.small_button("+").on_hover_text("Add channel").clicked() {
+                        cell.mode.channel_purposes.push(ChannelPurpose::NoType);
+                    }
+                });
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            cells.remove(i);
+        }
+        if ui.small_button("Add cell").clicked() {
+            let cell = match cells.last() {
+                Some(last) => {
+                    let mut cell = Cell::new(
+                        format!("Cell {}", cells.len() + 1),
+                        last.end(),
+                        last.mode.channel_purposes.clone(),
+                    );
+                    cell.position = [last.position[0] + 1., last.position[1]];
+                    cell
+                }
+                None => Cell::new("Cell 1", 0, vec![ChannelPurpose::ColR, ChannelPurpose::ColG, ChannelPurpose::ColB]),
+            };
+            cells.push(cell);
+        }
+    }
+
+    /// Editor for a capability table, one row per DMX range
+    fn capabilities_ui(ui: &mut egui::Ui, purpose: ChannelPurpose, caps: &mut Vec<Capability>) {
+        let mut remove = None;
+        for (i, cap) in caps.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut cap.name);
+                let (mut from, mut to) = (*cap.range.start(), *cap.range.end());
+                ui.add(egui::DragValue::new(&mut from));
+                ui.label("to");
+                ui.add(egui::DragValue::new(&mut to).clamp_range(from..=u8::MAX));
+                cap.range = from..=to.max(from);
+
+                let mut has_physical = cap.physical.is_some();
+                ui.checkbox(&mut has_physical, "physical");
+                if !has_physical {
+                    cap.physical = None;
+                } else if cap.physical.is_none() {
+                    cap.physical = Some(Physical {
+                        unit: PhysicalUnit::Percent,
+                        from: 0.,
+                        to: 100.,
+                    });
+                }
+                if let Some(p) = &mut cap.physical {
+                    ui.add(egui::DragValue::new(&mut p.from));
+                    ui.label("to");
+                    ui.add(egui::DragValue::new(&mut p.to));
+                    egui::ComboBox::from_id_source(ui.id().with(("unit", i)))
+                        .selected_text(p.unit.suffix())
+                        .show_ui(ui, |ui| {
+                            for u in PhysicalUnit::ALL {
+                                ui.selectable_value(&mut p.unit, *u, u.suffix());
+                            }
+                        });
+                }
+                if purpose == ChannelPurpose::ColorWheel {
+                    Self::slot_colors_ui(ui, &mut cap.colors);
+                }
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            caps.remove(i);
+        }
+        if ui.small_button("Add capability").clicked() {
+            let from = caps.last().map(|c| c.range.end().saturating_add(1)).unwrap_or(0);
+            caps.push(Capability::new(format!("Capability {}", caps.len() + 1), from..=u8::MAX));
+        }
+    }
+
+    /// Colours of a colour wheel slot, a second one makes it a split position
+    fn slot_colors_ui(ui: &mut egui::Ui, colors: &mut Vec<Rgb>) {
+        for c in colors.iter_mut() {
+            let mut rgb = c.0.map(|v| v as f32);
+            egui::color_picker::color_edit_button_rgb(ui, &mut rgb);
+            c.0 = rgb.map(|v| v as f64);
+        }
+        if colors.len() < 2 && ui.small_button("+").on_hover_text("Add a colour").clicked() {
+            colors.push(Rgb([1., 1., 1.]));
+        }
+        if !colors.is_empty() && ui.small_button("-").on_hover_text("Remove a colour").clicked() {
+            colors.pop();
+        }
+    }
+
+    /// Curves of the selected fixtures overriding the ones of their mode, shown for the first one
+    fn curves_ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState, history: &mut History) {
+        let first = match self.selection.first().and_then(|id| dmx_state.fixts.get(id)) {
+            Some(f) => f.clone(),
+            None => return,
+        };
+        let purposes: Vec<ChannelPurpose> = match dmx_state.fixture_mode(&first) {
+            Some(m) => m.channel_purposes.iter().copied().filter(|p| !p.is_fine() && *p != ChannelPurpose::NoType).collect(),
+            None => return,
+        };
+
+        let mut cmds = Vec::new();
+        for purpose in purposes {
+            let mut curve = first.curves.get(&purpose).cloned();
+            let before = curve.clone();
+            ui.horizontal(|ui| {
+                ui.label(format!("{purpose:?}"));
+                curve_ui(ui, ("fixture_curve", purpose), &mut curve, "From fixture type");
+            });
+            if curve == before {
+                continue;
+            }
+            for fixt_id in &self.selection {
+                let before = match dmx_state.fixts.get(fixt_id) {
+                    Some(f) => f.clone(),
+                    None => continue,
+                };
+                let mut after = before.clone();
+                match &curve {
+                    Some(c) => after.curves.insert(purpose, c.clone()),
+                    None => after.curves.remove(&purpose),
+                };
+                dmx_state.set_fixture(*fixt_id, Some(after.clone()));
+                cmds.push(Command::Patch {
+                    fixt_id: *fixt_id,
+                    before: Some(Box::new(before)),
+                    after: Some(Box::new(after)),
+                });
+            }
+        }
+        match cmds.len() {
+            0 => (),
+            1 => history.push(cmds.remove(0)),
+            _ => history.push(Command::Batch(cmds)),
+        }
+    }
+
+    /// Emitters taking over parts of a colour from red, green and blue, in order of extraction
+    fn color_mixing_ui(ui: &mut egui::Ui, type_id: usize, mixing: &mut ColorMixing) {
+        let mut remove = None;
+        for (i, emitter) in mixing.emitters.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source(("emitter", type_id, i))
+                    .selected_text(format!("{:?}", emitter.purpose))
+                    .show_ui(ui, |ui| {
+                        use ChannelPurpose::*;
+                        for p in [ColW, ColA, ColL, ColUv] {
+                            ui.selectable_value(&mut emitter.purpose, p, format!("{p:?}"));
+                        }
+                    });
+                let mut rgb = emitter.color.0.map(|c| c as f32);
+                egui::color_picker::color_edit_button_rgb(ui, &mut rgb);
+                emitter.color.0 = rgb.map(|c| c as f64);
+                ui.label("extraction");
+                ui.add(egui::Slider::new(&mut emitter.extraction, 0.0..=1.));
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            mixing.emitters.remove(i);
+        }
+        ui.horizontal(|ui| {
+            if ui.small_button("Add emitter").clicked() {
+                mixing.emitters.push(Emitter::new(ChannelPurpose::ColW, [1., 1., 1.]));
+            }
+            if ui.small_button("Reset").clicked() {
+                *mixing = ColorMixing::default();
+            }
+        });
+        ui.checkbox(&mut mixing.wheel_splits, "Use split colour wheel positions")
+            .on_hover_text("For fixtures following colours with their colour wheel");
+    }
+
+    /// Stage position and pan/tilt calibration of the first selected fixture
+    fn placement_ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState, history: &mut History) {
+        let (fixt_id, before) = match self.selection.first().and_then(|id| Some((*id, dmx_state.fixts.get(id)?))) {
+            Some((id, f)) => (id, f.clone()),
+            None => return,
+        };
+        let mut after = before.clone();
+        let mut placed = after.placement.is_some();
+        ui.checkbox(&mut placed, format!("Fixture {} is placed on stage", before.number));
+        match (placed, &mut after.placement) {
+            (false, placement) => *placement = None,
+            (true, placement @ None) => *placement = Some(Placement::default()),
+            (true, Some(p)) => {
+                egui::Grid::new("fixture_placement").show(ui, |ui| {
+                    ui.label("Position");
+                    for v in p.position.iter_mut() {
+                        ui.add(egui::DragValue::new(v).speed(0.05).suffix(" m"));
+                    }
+                    ui.end_row();
+                    ui.label("Rotation x/y/z");
+                    for v in p.rotation.iter_mut() {
+                        ui.add(egui::DragValue::new(v).clamp_range(-180.0..=180.).suffix("°"));
+                    }
+                    ui.end_row();
+                    ui.label("Pan offset");
+                    ui.add(egui::DragValue::new(&mut p.pan_offset).suffix("°"));
+                    ui.checkbox(&mut p.pan_invert, "invert");
+                    ui.end_row();
+                    ui.label("Tilt offset");
+                    ui.add(egui::DragValue::new(&mut p.tilt_offset).suffix("°"));
+                    ui.checkbox(&mut p.tilt_invert, "invert");
+                    ui.end_row();
+                    for (label, range) in [("Pan range", &mut p.pan_range), ("Tilt range", &mut p.tilt_range)] {
+                        ui.label(label);
+                        let mut own = range.is_some();
+                        ui.checkbox(&mut own, "override");
+                        match (own, range.as_mut()) {
+                            (true, Some(r)) => {
+                                ui.add(egui::DragValue::new(r).clamp_range(0.0..=720.).suffix("°"));
+                            }
+                            (true, None) => *range = Some(360.),
+                            (false, _) => *range = None,
+                        }
+                        ui.end_row();
+                    }
+                });
+            }
+        }
+
+        if after != before {
+            dmx_state.set_fixture(fixt_id, Some(after.clone()));
+            history.push(Command::Patch {
+                fixt_id,
+                before: Some(Box::new(before)),
+                after: Some(Box::new(after)),
+            });
+        }
+    }
+
+    /// Locating fixtures: highlighting the selection or stepping through a group
+    fn highlight_ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.selection.is_empty(), egui::Button::new("Highlight selection"))
+                .clicked()
+            {
+                if let Err(e) = dmx_state.highlight(&self.selection) {
+                    log::warn!("Could not highlight the selection: {e}");
+                }
+            }
+            if ui
+                .add_enabled(dmx_state.highlight.is_active(), egui::Button::new("Clear highlight"))
+                .clicked()
+            {
+                dmx_state.clear_highlight();
+            }
+            let dim = &mut dmx_state.highlight.dim_others;
+            let mut dimmed = dim.is_some();
+            ui.checkbox(&mut dimmed, "Dim others");
+            match (dimmed, dim.as_mut()) {
+                (true, Some(level)) => {
+                    ui.add(egui::Slider::new(level, 0.0..=1.));
+                }
+                (true, None) => *dim = Some(0.),
+                (false, _) => *dim = None,
+            }
+        });
+
+        let mut groups: Vec<(usize, String)> = dmx_state
+            .fixt_groups
+            .iter()
+            .map(|(id, g)| (*id, g.name.clone()))
+            .collect();
+        groups.sort_unstable();
+        ui.horizontal(|ui| {
+            let selected = self
+                .highlight_group
+                .and_then(|id| dmx_state.fixt_groups.get(&id))
+                .map(|g| g.name.clone())
+                .unwrap_or_default();
+            egui::ComboBox::from_id_source("highlight_group")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (id, name) in &groups {
+                        ui.selectable_value(&mut self.highlight_group, Some(*id), name);
+                    }
+                });
+            if let Some(group_id) = self.highlight_group {
+                let mut step = None;
+                if ui.button("Previous").clicked() {
+                    step = Some(-1);
+                }
+                if ui.button("Next").clicked() {
+                    step = Some(1);
+                }
+                if let Some(delta) = step {
+                    match dmx_state.highlight_step(group_id, delta) {
+                        Ok(fixt_id) => self.selection = vec![fixt_id],
+                        Err(e) => log::warn!("{e}"),
+                    }
+                }
+            }
+        });
+    }
+
+    /// Controls for the attributes of the selected fixtures, initialized from the first one
+    fn attributes_ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState, history: &mut History) {
+        let first = match self.selection.first() {
+            Some(id) => *id,
+            None => return,
+        };
+        let mode = dmx_state.fixts.get(&first).and_then(|f| dmx_state.fixture_mode(f)).cloned();
+        let degree_range = |purpose| {
+            dmx_state
+                .fixts
+                .get(&first)
+                .and_then(|f| dmx_state.degree_range(f, purpose))
+                .unwrap_or(360.)
+        };
+        let (pan_range, tilt_range) = (degree_range(ChannelPurpose::Pan), degree_range(ChannelPurpose::Tilt));
+        ui.horizontal(|ui| {
+            if ui.button("Home").on_hover_text("Set all channels to their home value").clicked() {
+                for fixt_id in &self.selection {
+                    if let Err(e) = dmx_state.home_fixture(Source::Manual, *fixt_id) {
+                        log::debug!("{e}");
+                    }
+                }
+            }
+            if ui.button("Park").on_hover_text("Freeze all channels at their current output").clicked() {
+                for fixt_id in &self.selection {
+                    if let Err(e) = dmx_state.park_fixture(*fixt_id) {
+                        log::debug!("{e}");
+                    }
+                }
+            }
+            if ui.button("Unpark").clicked() {
+                for fixt_id in &self.selection {
+                    if let Err(e) = dmx_state.unpark_fixture(*fixt_id) {
+                        log::debug!("{e}");
+                    }
+                }
+            }
+        });
+        let mut changed = Vec::new();
+
+        let mut kinds = vec![
+            AttributeKind::Intensity,
+            AttributeKind::Color,
+            AttributeKind::Pan,
+            AttributeKind::Tilt,
+        ];
+        if dmx_state.shutter(first).is_some() {
+            kinds.push(AttributeKind::Shutter);
+        }
+        if let Some(mode) = &mode {
+            kinds.extend(
+                mode.channel_purposes
+                    .iter()
+                    .filter(|p| !mode.capabilities(**p).is_empty())
+                    .map(|p| AttributeKind::Capability(*p)),
+            );
+        }
+
+        for kind in kinds {
+            let current = match dmx_state.attr(first, kind) {
+                Some(a) => a,
+                None => match kind {
+                    // The channel is at a value no capability covers, show it as unselected
+                    AttributeKind::Capability(purpose) => Attribute::Capability {
+                        purpose,
+                        name: String::new(),
+                        physical: None,
+                    },
+                    _ => continue,
+                },
+            };
+            let mut new = current.clone();
+            ui.horizontal(|ui| {
+                match kind {
+                    AttributeKind::Capability(p) => ui.label(format!("{p:?}")),
+                    _ => ui.label(format!("{kind:?}")),
+                };
+                match &mut new {
+                    Attribute::Intensity(v) => {
+                        ui.add(egui::Slider::new(v, 0.0..=1.0));
+                    }
+                    Attribute::Color(c) => {
+                        let mut rgba: egui::Rgba = (*c).into();
+                        egui::color_picker::color_edit_button_rgba(
+                            ui,
+                            &mut rgba,
+                            egui::color_picker::Alpha::Opaque,
+                        );
+                        *c = rgba.into();
+                    }
+                    Attribute::Pan(deg) => {
+                        ui.add(egui::Slider::new(deg, -pan_range / 2.0..=pan_range / 2.).suffix("°"));
+                    }
+                    Attribute::Tilt(deg) => {
+                        ui.add(egui::Slider::new(deg, -tilt_range / 2.0..=tilt_range / 2.).suffix("°"));
+                    }
+                    Attribute::Shutter(state) => {
+                        let rate = state.rate().unwrap_or(1.);
+                        egui::ComboBox::from_id_source("shutter_state")
+                            .selected_text(state.name())
+                            .show_ui(ui, |ui| {
+                                for name in ["Open", "Closed", "Strobe", "Pulse", "Random"] {
+                                    let candidate = ShutterState::from_name(name, rate).unwrap();
+                                    if dmx_state.shutter_capability(first, candidate).is_some() {
+                                        ui.selectable_value(state, candidate, name);
+                                    }
+                                }
+                            });
+                        let rates = dmx_state.shutter_rates(first, *state);
+                        if let (
+                            ShutterState::Strobe(hz) | ShutterState::Pulse(hz) | ShutterState::Random(hz),
+                            Some(rates),
+                        ) = (state, rates)
+                        {
+                            ui.add(egui::Slider::new(hz, rates).suffix(" Hz"));
+                        }
+                    }
+                    Attribute::Point(p) => {
+                        for v in p.iter_mut() {
+                            ui.add(egui::DragValue::new(v).speed(0.05).suffix(" m"));
+                        }
+                    }
+                    Attribute::Capability { purpose, name, physical } => {
+                        let caps = mode.as_ref().map(|m| m.capabilities(*purpose)).unwrap_or_default();
+                        egui::ComboBox::from_id_source(("capability", *purpose))
+                            .selected_text(name.as_str())
+                            .show_ui(ui, |ui| {
+                                for cap in caps {
+                                    if ui.selectable_label(*name == cap.name, &cap.name).clicked() {
+                                        *name = cap.name.clone();
+                                        *physical = cap.physical.as_ref().map(|p| p.from);
+                                    }
+                                }
+                            });
+                        let phys = caps.iter().find(|c| c.name == *name).and_then(|c| c.physical.as_ref());
+                        if let (Some(p), Some(v)) = (phys, physical) {
+                            let range = p.from.min(p.to)..=p.from.max(p.to);
+                            ui.add(egui::Slider::new(v, range).suffix(p.unit.suffix()));
+                        }
+                    }
+                }
+            });
+            if new != current {
+                changed.push(new);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Point at");
+            for v in self.point.iter_mut() {
+                ui.add(egui::DragValue::new(v).speed(0.05).suffix(" m"));
+            }
+            if ui.button("Point selection").clicked() {
+                changed.push(Attribute::Point(self.point));
+            }
+            if ui
+                .button("Refocus rig")
+                .on_hover_text("Point all fixtures with a placement at this point")
+                .clicked()
+            {
+                let count = dmx_state.refocus(Source::Manual, self.point);
+                log::info!("Refocused {count} fixture(s) on {:?}", self.point);
+            }
+        });
+
+        for attr in changed {
+            let mut cmds = Vec::new();
+            for fixt_id in &self.selection {
+                // Cells have virtual intensities of their own
+                let cells = dmx_state.fixts.get(fixt_id).map(|f| f.cells.clone()).unwrap_or_default();
+                let ids: Vec<usize> = std::iter::once(*fixt_id).chain(cells).collect();
+                let manual = |d: &DmxState, id: usize| d.virtual_intensity.get(Source::Manual, id);
+                let before: Vec<_> = ids.iter().map(|id| manual(dmx_state, *id)).collect();
+                if let Err(e) = dmx_state.set_attr(Source::Manual, *fixt_id, attr.clone()) {
+                    log::debug!("{e}");
+                }
+                for (id, before) in ids.into_iter().zip(before) {
+                    let after = manual(dmx_state, id);
+                    if before != after {
+                        cmds.push(Command::VirtualIntensity {
+                            fixt_id: id,
+                            before,
+                            after,
+                        });
+                    }
+                }
+            }
+            match cmds.len() {
+                0 => (),
+                1 => history.push(cmds.remove(0)),
+                _ => history.push(Command::Batch(cmds)),
+            }
+        }
+    }
+}
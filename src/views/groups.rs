@@ -0,0 +1,131 @@
+use crate::dmx::{DmxState, FixtureGroup};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct GroupsView {
+    seed: u64,
+    nth: usize,
+    /// Names being typed that are not valid yet, by group id
+    names: HashMap<usize, String>,
+}
+
+impl Default for GroupsView {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            nth: 3,
+            names: HashMap::new(),
+        }
+    }
+}
+
+impl GroupsView {
+    /// Group list, `selection` is the fixture selection groups are created from and selected into
+    pub fn ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState, selection: &mut Vec<usize>) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!selection.is_empty(), egui::Button::new("New group from selection"))
+                .clicked()
+            {
+                let name = format!("Group {}", dmx_state.group_next_id + 1);
+                dmx_state.add_group(FixtureGroup::new(name, selection.clone()));
+            }
+            ui.label("Seed");
+            ui.add(egui::DragValue::new(&mut self.seed));
+            ui.label("Every");
+            ui.add(egui::DragValue::new(&mut self.nth).clamp_range(1..=64));
+        });
+
+        let mut ids: Vec<usize> = dmx_state.fixt_groups.keys().copied().collect();
+        ids.sort_unstable();
+        let mut remove = None;
+        let mut rename = None;
+        for group_id in ids {
+            // Members that aren't patched stay in the group, so undoing their removal restores them
+            let labels: Vec<String> = dmx_state.fixt_groups[&group_id]
+                .members
+                .iter()
+                .map(|id| match dmx_state.fixts.get(id) {
+                    Some(f) => format!("{} {}", f.number, f.name),
+                    None => format!("fixture {id} (not patched)"),
+                })
+                .collect();
+            let group = dmx_state.fixt_groups.get_mut(&group_id).unwrap();
+
+            egui::CollapsingHeader::new(format!("{} ({})", group.name, group.members.len()))
+                .id_source(("fixture_group", group_id))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let mut name = self.names.get(&group_id).unwrap_or(&group.name).clone();
+                        if ui.text_edit_singleline(&mut name).changed() {
+                            rename = Some((group_id, name));
+                        }
+                        if self.names.contains_key(&group_id) {
+                            ui.colored_label(egui::Color32::RED, "Name is empty or taken");
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Select").clicked() {
+                            *selection = group.members.clone();
+                        }
+                        if ui.button("Odd").clicked() {
+                            *selection = group.odd();
+                        }
+                        if ui.button("Even").clicked() {
+                            *selection = group.even();
+                        }
+                        if ui.button(format!("Every {}", self.nth)).clicked() {
+                            *selection = group.every_nth(self.nth, 0);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!selection.is_empty(), egui::Button::new("Add selection")).clicked() {
+                            selection.iter().for_each(|id| group.add(*id));
+                        }
+                        if ui.button("Reverse").clicked() {
+                            group.reverse();
+                        }
+                        if ui.button("Shuffle").clicked() {
+                            group.shuffle(self.seed);
+                        }
+                        if ui.button("Delete group").clicked() {
+                            remove = Some(group_id);
+                        }
+                    });
+
+                    let mut moved = None;
+                    let mut removed = None;
+                    for (i, label) in labels.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(label);
+                            if ui.add_enabled(i > 0, egui::Button::new("⏶").small()).clicked() {
+                                moved = Some((i, i - 1));
+                            }
+                            if ui.add_enabled(i + 1 < labels.len(), egui::Button::new("⏷").small()).clicked() {
+                                moved = Some((i, i + 1));
+                            }
+                            if ui.small_button("x").clicked() {
+                                removed = Some(group.members[i]);
+                            }
+                        });
+                    }
+                    if let Some((from, to)) = moved {
+                        group.move_member(from, to);
+                    }
+                    if let Some(id) = removed {
+                        group.remove(id);
+                    }
+                });
+        }
+        if let Some((id, name)) = rename {
+            match dmx_state.rename_group(id, &name) {
+                Ok(()) => self.names.remove(&id),
+                Err(_) => self.names.insert(id, name),
+            };
+        }
+        if let Some(id) = remove {
+            self.names.remove(&id);
+            dmx_state.remove_group(id);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::dmx::{DmxState, MasterControl, Submaster};
+
+/// Grand master, blackout and submaster faders with flash buttons
+pub fn masters_ui(ui: &mut egui::Ui, dmx_state: &mut DmxState) {
+    let mut controls = Vec::new();
+
+    ui.horizontal(|ui| {
+        let blackout = dmx_state.masters.blackout;
+        if ui.selectable_label(blackout, "Blackout").clicked() {
+            controls.push(MasterControl::ToggleBlackout);
+        }
+        if ui.button("Add submaster").clicked() {
+            let name = format!("Sub {}", dmx_state.masters.submasters.len() + 1);
+            let group = first_group(dmx_state);
+            dmx_state.masters.submasters.push(Submaster::new(name, group));
+        }
+    });
+
+    let mut groups: Vec<String> = dmx_state.fixt_groups.values().map(|g| g.name.clone()).collect();
+    groups.sort_unstable();
+
+    let mut remove = None;
+    egui::ScrollArea::horizontal().show(ui, |ui| {
+        ui.horizontal(|ui| {
+            let masters = &mut dmx_state.masters;
+            let mut grand = masters.grand;
+            if let Some(flash) = fader_ui(ui, "GM", &mut grand, masters.grand_flash) {
+                controls.push(MasterControl::GrandFlash(flash));
+            }
+            if grand != masters.grand {
+                controls.push(MasterControl::Grand(grand));
+            }
+            ui.separator();
+
+            for (i, sub) in masters.submasters.iter_mut().enumerate() {
+                ui.vertical(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut sub.name).desired_width(60.));
+                    egui::ComboBox::from_id_source(("submaster_group", i))
+                        .width(60.)
+                        .selected_text(&sub.group)
+                        .show_ui(ui, |ui| {
+                            for g in &groups {
+                                ui.selectable_value(&mut sub.group, g.clone(), g);
+                            }
+                        });
+                    if let Some(flash) = fader_ui(ui, &sub.name, &mut sub.level, sub.flash) {
+                        sub.flash = flash;
+                    }
+                    if ui.small_button("x").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+        });
+    });
+
+    if let Some(i) = remove {
+        dmx_state.masters.submasters.remove(i);
+    }
+    for ctl in controls {
+        if let Err(e) = dmx_state.control(ctl) {
+            log::debug!("{e}");
+        }
+    }
+}
+
+/// Vertical fader with a flash button below. Returns the new flash state when it changed.
+fn fader_ui(ui: &mut egui::Ui, label: &str, level: &mut f64, flash: bool) -> Option<bool> {
+    ui.vertical(|ui| {
+        ui.label(label);
+        ui.add(egui::Slider::new(level, 0.0..=1.).vertical().show_value(false));
+        ui.label(format!("{:.0}%", *level * 100.));
+        let held = ui.button("Flash").is_pointer_button_down_on();
+        (held != flash).then_some(held)
+    })
+    .inner
+}
+
+fn first_group(dmx_state: &DmxState) -> String {
+    dmx_state
+        .fixt_groups
+        .values()
+        .map(|g| g.name.clone())
+        .min()
+        .unwrap_or_default()
+}
@@ -0,0 +1,7 @@
+pub mod curve;
+pub mod fixtures;
+pub mod groups;
+pub mod masters;
+pub mod modifiers;
+pub mod output;
+pub mod pixel_map;
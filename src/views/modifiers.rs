@@ -0,0 +1,213 @@
+use crate::dmx::{ChannelModifier, ChannelPurpose, DmxState, UNIVERSE_SIZE};
+
+/// Channel and fixture limits, inversion and parked channels of the show
+#[derive(Debug, Clone, Default)]
+pub struct ModifiersView {
+    universe: usize,
+    /// Channel number starting at 1 like in the monitor
+    channel: usize,
+    fixt: Option<usize>,
+    purpose: Option<ChannelPurpose>,
+}
+
+impl ModifiersView {
+    pub fn ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState) {
+        ui.label("Channels");
+        self.channels_ui(ui, dmx_state);
+        ui.separator();
+        ui.label("Fixtures");
+        self.fixtures_ui(ui, dmx_state);
+    }
+
+    fn channels_ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState) {
+        let mut rows: Vec<(usize, usize)> = dmx_state
+            .modifiers
+            .channels
+            .iter()
+            .flat_map(|(un, chans)| chans.keys().map(move |ch| (*un, *ch)))
+            .chain(
+                dmx_state
+                    .modifiers
+                    .parked
+                    .iter()
+                    .flat_map(|(un, chans)| chans.keys().map(move |ch| (*un, *ch))),
+            )
+            .collect();
+        rows.sort_unstable();
+        rows.dedup();
+
+        let mut park = Vec::new();
+        let mut unpark = Vec::new();
+        let mut remove = Vec::new();
+        egui::Grid::new("channel_modifiers").striped(true).show(ui, |ui| {
+            ui.label("Universe");
+            ui.label("Channel");
+            ui.label("Min");
+            ui.label("Max");
+            ui.label("Invert");
+            ui.label("Parked");
+            ui.end_row();
+
+            for (un_id, ch) in rows {
+                ui.label(un_id.to_string());
+                ui.label((ch + 1).to_string());
+                match dmx_state.modifiers.channels.get_mut(&un_id).and_then(|c| c.get_mut(&ch)) {
+                    Some(m) => modifier_ui(ui, m),
+                    None => {
+                        ui.label("");
+                        ui.label("");
+                        ui.label("");
+                    }
+                }
+                let parked = dmx_state.modifiers.parked(un_id, ch);
+                ui.horizontal(|ui| {
+                    let mut is_parked = parked.is_some();
+                    let mut v = parked.unwrap_or_default();
+                    ui.checkbox(&mut is_parked, "");
+                    if is_parked {
+                        ui.add(egui::DragValue::new(&mut v));
+                    }
+                    match (parked, is_parked) {
+                        (Some(old), true) if old != v => park.push((un_id, ch, v)),
+                        (Some(_), false) => unpark.push((un_id, ch)),
+                        (None, true) => park.push((un_id, ch, v)),
+                        _ => {}
+                    }
+                });
+                if ui.small_button("x").clicked() {
+                    remove.push((un_id, ch));
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Universe");
+            ui.add(egui::DragValue::new(&mut self.universe));
+            ui.label("Channel");
+            ui.add(egui::DragValue::new(&mut self.channel).clamp_range(1..=UNIVERSE_SIZE));
+            let exists = dmx_state.universes.contains_key(&self.universe);
+            let ch = self.channel.max(1) - 1;
+            if ui.add_enabled(exists, egui::Button::new("Add limits")).clicked() {
+                dmx_state
+                    .modifiers
+                    .channels
+                    .entry(self.universe)
+                    .or_default()
+                    .entry(ch)
+                    .or_default();
+            }
+            if ui
+                .add_enabled(exists, egui::Button::new("Park"))
+                .on_hover_text("Freeze the channel at its current output")
+                .clicked()
+            {
+                let v = dmx_state.output_frame(self.universe).map(|f| f[ch]).unwrap_or_default();
+                park.push((self.universe, ch, v));
+            }
+        });
+
+        for (un_id, ch, v) in park {
+            if let Err(e) = dmx_state.park(un_id, ch, v) {
+                log::debug!("{e}");
+            }
+        }
+        for (un_id, ch) in unpark {
+            dmx_state.unpark(un_id, ch);
+        }
+        for (un_id, ch) in remove {
+            dmx_state.modifiers.set_channel(un_id, ch, None);
+            dmx_state.unpark(un_id, ch);
+        }
+    }
+
+    fn fixtures_ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState) {
+        let mut rows: Vec<(usize, usize, ChannelPurpose)> = dmx_state
+            .modifiers
+            .fixtures
+            .iter()
+            .flat_map(|(id, purposes)| {
+                let number = dmx_state.fixts.get(id).map(|f| f.number).unwrap_or(usize::MAX);
+                purposes.keys().map(move |p| (number, *id, *p))
+            })
+            .collect();
+        rows.sort_unstable_by_key(|(number, id, p)| (*number, *id, format!("{p:?}")));
+
+        let mut remove = Vec::new();
+        egui::Grid::new("fixture_modifiers").striped(true).show(ui, |ui| {
+            ui.label("Fixture");
+            ui.label("Channel");
+            ui.label("Min");
+            ui.label("Max");
+            ui.label("Invert");
+            ui.end_row();
+
+            for (_, fixt_id, purpose) in rows {
+                ui.label(fixture_label(dmx_state, fixt_id));
+                ui.label(format!("{purpose:?}"));
+                if let Some(m) = dmx_state.modifiers.fixtures.get_mut(&fixt_id).and_then(|f| f.get_mut(&purpose)) {
+                    modifier_ui(ui, m);
+                }
+                if ui.small_button("x").clicked() {
+                    remove.push((fixt_id, purpose));
+                }
+                ui.end_row();
+            }
+        });
+        for (fixt_id, purpose) in remove {
+            dmx_state.modifiers.set_fixture(fixt_id, purpose, None);
+        }
+
+        let mut fixts: Vec<(usize, usize)> = dmx_state.fixts.iter().map(|(id, f)| (f.number, *id)).collect();
+        fixts.sort_unstable();
+        let purposes: Vec<ChannelPurpose> = self
+            .fixt
+            .and_then(|id| dmx_state.fixts.get(&id))
+            .and_then(|f| dmx_state.fixture_mode(f))
+            .map(|m| m.channel_purposes.iter().copied().filter(|p| !p.is_fine()).collect())
+            .unwrap_or_default();
+        ui.horizontal(|ui| {
+            let selected = self.fixt.map(|id| fixture_label(dmx_state, id)).unwrap_or_default();
+            egui::ComboBox::from_id_source("fixture_modifier_fixture")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (_, id) in &fixts {
+                        ui.selectable_value(&mut self.fixt, Some(*id), fixture_label(dmx_state, *id));
+                    }
+                });
+            egui::ComboBox::from_id_source("fixture_modifier_purpose")
+                .selected_text(self.purpose.map(|p| format!("{p:?}")).unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for p in &purposes {
+                        ui.selectable_value(&mut self.purpose, Some(*p), format!("{p:?}"));
+                    }
+                });
+            let valid = self.purpose.filter(|p| purposes.contains(p));
+            if let (Some(fixt_id), Some(purpose)) = (self.fixt, valid) {
+                if ui.button("Add limits").clicked() {
+                    dmx_state
+                        .modifiers
+                        .fixtures
+                        .entry(fixt_id)
+                        .or_default()
+                        .entry(purpose)
+                        .or_default();
+                }
+            }
+        });
+    }
+}
+
+/// Min, max and invert cells of a grid row
+fn modifier_ui(ui: &mut egui::Ui, m: &mut ChannelModifier) {
+    ui.add(egui::DragValue::new(&mut m.min).clamp_range(0..=m.max));
+    ui.add(egui::DragValue::new(&mut m.max).clamp_range(m.min..=u8::MAX));
+    ui.checkbox(&mut m.invert, "");
+}
+
+fn fixture_label(dmx_state: &DmxState, fixt_id: usize) -> String {
+    match dmx_state.fixts.get(&fixt_id) {
+        Some(f) => format!("{} {}", f.number, f.name),
+        None => format!("{fixt_id} (not patched)"),
+    }
+}
@@ -0,0 +1,137 @@
+use crate::dmx::{DmxState, MergeMode, OutputRoute, SafetyLimiter, Source, UniverseMeta};
+use crate::history::{Command, History};
+
+/// Universes with their metadata, output routing and the merge configuration of their layers
+pub fn output_ui(ui: &mut egui::Ui, dmx_state: &mut DmxState, history: &mut History) {
+    if ui.button("Add universe").clicked() {
+        let label = format!("Universe {}", dmx_state.universe_ids().last().map(|id| id + 2).unwrap_or(1));
+        let id = dmx_state.add_universe(UniverseMeta::new(label));
+        history.push(Command::Universe {
+            id,
+            before: None,
+            after: dmx_state.universe_snapshot(id).map(Box::new),
+        });
+    }
+
+    let mut remove = None;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for un_id in dmx_state.universe_ids() {
+            let un = dmx_state.universes.get_mut(&un_id).unwrap();
+            egui::CollapsingHeader::new(format!("{un_id}: {}", un.meta.label))
+                .id_source(("universe", un_id))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Label");
+                        ui.text_edit_singleline(&mut un.meta.label);
+                        let mut rgba: egui::Rgba = un.meta.color.into();
+                        egui::color_picker::color_edit_button_rgba(ui, &mut rgba, egui::color_picker::Alpha::Opaque);
+                        un.meta.color = rgba.into();
+                        if ui.button("Remove universe").clicked() {
+                            remove = Some(un_id);
+                        }
+                    });
+                    routes_ui(ui, un_id, &mut un.meta.routes);
+
+                    egui::Grid::new(("universe_layers", un_id)).striped(true).show(ui, |ui| {
+                        ui.label("Source");
+                        ui.label("Merge");
+                        ui.label("Priority");
+                        ui.end_row();
+
+                        let layers: Vec<(Source, MergeMode, u8)> = un
+                            .layers()
+                            .layers()
+                            .iter()
+                            .map(|l| (l.source, l.mode, l.priority))
+                            .collect();
+                        for (src, mode, prio) in layers {
+                            let (mut new_mode, mut new_prio) = (mode, prio);
+                            ui.label(format!("{src:?}"));
+                            egui::ComboBox::from_id_source(("layer_mode", un_id, src))
+                                .selected_text(format!("{new_mode:?}"))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut new_mode, MergeMode::Htp, "Htp");
+                                    ui.selectable_value(&mut new_mode, MergeMode::Ltp, "Ltp");
+                                });
+                            ui.add(egui::DragValue::new(&mut new_prio));
+                            ui.end_row();
+
+                            if new_mode != mode || new_prio != prio {
+                                un.configure_layer(src, |l| {
+                                    l.mode = new_mode;
+                                    l.priority = new_prio;
+                                });
+                            }
+                        }
+                    });
+                });
+        }
+    });
+
+    if let Some(id) = remove {
+        history.push(Command::Universe {
+            id,
+            before: dmx_state.remove_universe(id).map(Box::new),
+            after: None,
+        });
+    }
+}
+
+fn routes_ui(ui: &mut egui::Ui, un_id: usize, routes: &mut Vec<OutputRoute>) {
+    let mut remove = None;
+    for (i, route) in routes.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            match route {
+                OutputRoute::Artnet { port_address } => {
+                    ui.label("Art-Net port address");
+                    ui.add(egui::DragValue::new(port_address).clamp_range(0..=0x7fff));
+                }
+                OutputRoute::Sacn { universe } => {
+                    ui.label("sACN universe");
+                    ui.add(egui::DragValue::new(universe).clamp_range(1..=63999));
+                }
+            }
+            if ui.small_button("x").clicked() {
+                remove = Some(i);
+            }
+        });
+    }
+    if let Some(i) = remove {
+        routes.remove(i);
+    }
+    ui.horizontal(|ui| {
+        if ui.small_button("Add Art-Net output").clicked() {
+            routes.push(OutputRoute::Artnet {
+                port_address: un_id as u16,
+            });
+        }
+        if ui.small_button("Add sACN output").clicked() {
+            routes.push(OutputRoute::Sacn {
+                universe: un_id as u16 + 1,
+            });
+        }
+    });
+}
+
+/// Limits of the photosensitive safety limiter applied to all output
+pub fn safety_ui(ui: &mut egui::Ui, safety: &mut SafetyLimiter) {
+    let cfg = &mut safety.config;
+    ui.checkbox(&mut cfg.enabled, "Enabled");
+    ui.add_enabled_ui(cfg.enabled, |ui| {
+        egui::Grid::new("safety_limits").show(ui, |ui| {
+            ui.label("Max. flashes per fixture");
+            ui.add(egui::Slider::new(&mut cfg.max_flash_hz, 0.5..=25.).suffix(" Hz"));
+            ui.end_row();
+            ui.label("Max. flashes of a large area");
+            ui.add(egui::Slider::new(&mut cfg.max_area_flash_hz, 0.5..=25.).suffix(" Hz"));
+            ui.end_row();
+            ui.label("Large area");
+            ui.add(egui::Slider::new(&mut cfg.area_share, 0.05..=1.).text("of fixtures"));
+            ui.end_row();
+            ui.label("Flash threshold");
+            ui.add(egui::Slider::new(&mut cfg.threshold, 0.01..=1.).text("intensity"));
+            ui.end_row();
+        });
+    });
+    ui.label(format!("{} interventions", safety.interventions));
+}
@@ -0,0 +1,245 @@
+use crate::color::Rgba;
+use crate::dmx::{DmxState, Generator, ImageLoader, ImageSource, MappedFixture, PixelMap, PixelSource};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct PixelMapView {
+    /// Index of the pixel map being edited
+    map: usize,
+}
+
+impl PixelMapView {
+    /// Editor for the pixel maps, `selection` is the fixture selection added to a map
+    pub fn ui(&mut self, ui: &mut egui::Ui, dmx_state: &mut DmxState, selection: &[usize]) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("pixel_map")
+                .selected_text(dmx_state.pixel_maps.get(self.map).map(|m| m.name.as_str()).unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    for (i, m) in dmx_state.pixel_maps.iter().enumerate() {
+                        ui.selectable_value(&mut self.map, i, &m.name);
+                    }
+                });
+            if ui.button("New pixel map").clicked() {
+                let name = format!("Pixel map {}", dmx_state.pixel_maps.len() + 1);
+                dmx_state.pixel_maps.push(PixelMap::new(name));
+                self.map = dmx_state.pixel_maps.len() - 1;
+            }
+            if ui
+                .add_enabled(self.map < dmx_state.pixel_maps.len(), egui::Button::new("Remove"))
+                .clicked()
+            {
+                dmx_state.pixel_maps.remove(self.map);
+            }
+        });
+        if self.map >= dmx_state.pixel_maps.len() {
+            self.map = dmx_state.pixel_maps.len().saturating_sub(1);
+            return;
+        }
+
+        let mut map = dmx_state.pixel_maps[self.map].clone();
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut map.name);
+            ui.checkbox(&mut map.enabled, "Enabled");
+            ui.label("Size");
+            ui.add(egui::DragValue::new(&mut map.size[0]).speed(0.1).clamp_range(0.1..=1000.));
+            ui.add(egui::DragValue::new(&mut map.size[1]).speed(0.1).clamp_range(0.1..=1000.));
+        });
+        source_ui(ui, &mut map.source, &dmx_state.images);
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!selection.is_empty(), egui::Button::new("Add selection"))
+                .on_hover_text("Place the selected fixtures in a row right of the mapped ones")
+                .clicked()
+            {
+                let mut x = map
+                    .fixts
+                    .iter()
+                    .flat_map(|m| dmx_state.pixel_positions(m))
+                    .map(|(_, [x, _])| x + 1.)
+                    .fold(0.5, f64::max);
+                for fixt_id in selection {
+                    if map.fixts.iter().any(|m| m.fixt_id == *fixt_id) {
+                        continue;
+                    }
+                    let mapped = MappedFixture::new(*fixt_id, [x, 0.5]);
+                    x = dmx_state
+                        .pixel_positions(&mapped)
+                        .iter()
+                        .map(|(_, [x, _])| x + 1.)
+                        .fold(x + 1., f64::max);
+                    map.fixts.push(mapped);
+                }
+            }
+            if ui.button("Fit size").on_hover_text("Size the layout to the mapped fixtures").clicked() {
+                let positions: Vec<[f64; 2]> = map
+                    .fixts
+                    .iter()
+                    .flat_map(|m| dmx_state.pixel_positions(m))
+                    .map(|(_, p)| p)
+                    .collect();
+                if !positions.is_empty() {
+                    map.size = [0, 1].map(|i| positions.iter().map(|p| p[i] + 0.5).fold(0.1, f64::max));
+                }
+            }
+        });
+
+        let mut remove = None;
+        egui::Grid::new("pixel_map_fixtures").striped(true).show(ui, |ui| {
+            ui.label("Fixture");
+            ui.label("Position");
+            ui.label("Cell size");
+            ui.label("Rotation");
+            ui.end_row();
+            for (i, mapped) in map.fixts.iter_mut().enumerate() {
+                ui.label(match dmx_state.fixts.get(&mapped.fixt_id) {
+                    Some(f) => format!("{} {}", f.number, f.name),
+                    None => format!("{} (not patched)", mapped.fixt_id),
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut mapped.position[0]).speed(0.1).prefix("x "));
+                    ui.add(egui::DragValue::new(&mut mapped.position[1]).speed(0.1).prefix("y "));
+                });
+                ui.add(egui::DragValue::new(&mut mapped.cell_size).speed(0.05).clamp_range(0.01..=100.));
+                ui.add(egui::DragValue::new(&mut mapped.rotation).clamp_range(-360.0..=360.).suffix("°"));
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            map.fixts.remove(i);
+        }
+
+        preview_ui(ui, dmx_state, &map);
+        dmx_state.pixel_maps[self.map] = map;
+    }
+}
+
+fn source_ui(ui: &mut egui::Ui, source: &mut PixelSource, images: &ImageLoader) {
+    ui.horizontal(|ui| {
+        let selected = match source {
+            PixelSource::Generator(g) => g.name(),
+            PixelSource::Buffer(_) => "Buffer",
+            PixelSource::Image(_) => "Image",
+        };
+        egui::ComboBox::from_id_source("pixel_source")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for name in Generator::NAMES {
+                    if ui.selectable_label(selected == *name, *name).clicked() && selected != *name {
+                        *source = PixelSource::Generator(Generator::from_name(name).unwrap());
+                    }
+                }
+                if ui.selectable_label(selected == "Image", "Image").clicked() && selected != "Image" {
+                    *source = PixelSource::Image(ImageSource::new(""));
+                }
+            });
+        match source {
+            PixelSource::Generator(Generator::Solid(color)) => color_ui(ui, color),
+            PixelSource::Generator(Generator::Gradient { from, to, angle }) => {
+                color_ui(ui, from);
+                color_ui(ui, to);
+                ui.add(egui::DragValue::new(angle).clamp_range(0.0..=360.).suffix("°"));
+            }
+            PixelSource::Generator(Generator::Rainbow { angle, scale, speed }) => {
+                ui.add(egui::DragValue::new(angle).clamp_range(0.0..=360.).suffix("°"));
+                ui.add(egui::DragValue::new(scale).speed(0.05).clamp_range(0.0..=16.).prefix("× "));
+                ui.add(egui::DragValue::new(speed).speed(0.01).clamp_range(-10.0..=10.).suffix(" Hz"));
+            }
+            PixelSource::Generator(Generator::Wave {
+                color,
+                background,
+                angle,
+                scale,
+                speed,
+            }) => {
+                color_ui(ui, color);
+                color_ui(ui, background);
+                ui.add(egui::DragValue::new(angle).clamp_range(0.0..=360.).suffix("°"));
+                ui.add(egui::DragValue::new(scale).speed(0.05).clamp_range(0.0..=16.).prefix("× "));
+                ui.add(egui::DragValue::new(speed).speed(0.01).clamp_range(-10.0..=10.).suffix(" Hz"));
+            }
+            PixelSource::Buffer(b) => {
+                ui.label(format!("{}x{} pixels from the graph", b.width, b.height));
+            }
+            PixelSource::Image(_) => {}
+        }
+    });
+    if let PixelSource::Image(image) = source {
+        image_ui(ui, image, images);
+    }
+}
+
+fn image_ui(ui: &mut egui::Ui, image: &mut ImageSource, images: &ImageLoader) {
+    ui.horizontal(|ui| {
+        ui.label("File");
+        ui.text_edit_singleline(&mut image.path)
+            .on_hover_text("Image, GIF or one file of a numbered sequence like frame_001.png");
+        if ui.button("Load").on_hover_text("Load the file again, it is decoded in the background").clicked() {
+            images.reload(&image.path);
+            image.frames = Arc::default();
+        }
+        if ui.button("Restart").clicked() {
+            image.started = None;
+        }
+        match image.frames.first() {
+            Some(f) => ui.label(format!(
+                "{} frames, {}x{}, {:.2} s",
+                image.frames.len(),
+                f.image.width(),
+                f.image.height(),
+                image.duration()
+            )),
+            None => ui.label("Not loaded"),
+        };
+    });
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut image.speed).speed(0.01).clamp_range(-10.0..=10.).prefix("speed × "));
+        ui.checkbox(&mut image.looping, "Loop");
+        ui.add(egui::DragValue::new(&mut image.fps).speed(0.1).clamp_range(0.1..=120.).suffix(" fps"))
+            .on_hover_text("Frame rate of stills and numbered sequences");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Crop");
+        for (v, prefix) in image.crop.iter_mut().zip(["left ", "top ", "right ", "bottom "]) {
+            ui.add(egui::DragValue::new(v).speed(0.01).clamp_range(0.0..=1.).prefix(prefix));
+        }
+        ui.label("Scale");
+        for (v, prefix) in image.scale.iter_mut().zip(["x ", "y "]) {
+            ui.add(egui::DragValue::new(v).speed(0.01).clamp_range(0.01..=100.).prefix(prefix));
+        }
+        ui.label("Offset");
+        for (v, prefix) in image.offset.iter_mut().zip(["x ", "y "]) {
+            ui.add(egui::DragValue::new(v).speed(0.01).clamp_range(-100.0..=100.).prefix(prefix));
+        }
+    });
+}
+
+fn color_ui(ui: &mut egui::Ui, color: &mut Rgba) {
+    let mut rgba: egui::Rgba = (*color).into();
+    egui::color_picker::color_edit_button_rgba(ui, &mut rgba, egui::color_picker::Alpha::Opaque);
+    *color = rgba.into();
+}
+
+/// Layout of the pixel map with every pixel in the colour it currently samples
+fn preview_ui(ui: &mut egui::Ui, dmx_state: &DmxState, map: &PixelMap) {
+    let [w, h] = map.size.map(|s| s.max(0.1));
+    let scale = (ui.available_width() as f64 / w).min(300. / h);
+    let (rect, _) = ui.allocate_exact_size(egui::vec2((w * scale) as f32, (h * scale) as f32), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0., ui.style().visuals.extreme_bg_color);
+
+    let t = ui.input().time;
+    let radius = (scale as f32 * 0.4).clamp(1., 12.);
+    for mapped in &map.fixts {
+        for (_, [x, y]) in dmx_state.pixel_positions(mapped) {
+            let color: [u8; 4] = map.source.sample(x / w, y / h, t).into();
+            let center = rect.left_top() + egui::vec2((x * scale) as f32, (y * scale) as f32);
+            painter.circle_filled(center, radius, egui::Color32::from_rgb(color[0], color[1], color[2]));
+        }
+    }
+    ui.ctx().request_repaint();
+}